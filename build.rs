@@ -0,0 +1,102 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Shader sources, compiled into `$OUT_DIR/shaders/<backend>/<name>.bin`
+const SOURCE_DIR: &str = "shaders/src";
+
+/// Backend directory, shaderc platform and profiles for vertex, fragment and compute
+const TARGETS: &[(&str, &str, [&str; 3])] = &[
+    ("glsl", "linux", ["440", "440", "440"]),
+    ("essl", "android", ["320_es", "320_es", "320_es"]),
+    ("spirv", "linux", ["spirv", "spirv", "spirv"]),
+    ("metal", "osx", ["metal", "metal", "metal"]),
+    ("dx11", "windows", ["vs_5_0", "ps_5_0", "cs_5_0"]),
+];
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", SOURCE_DIR);
+    println!("cargo:rerun-if-env-changed=SHADERC");
+    println!("cargo:rerun-if-env-changed=BGFX_SHADER_INCLUDE");
+
+    // Precompiled binaries are used as is unless the feature is enabled
+    if env::var_os("CARGO_FEATURE_COMPILE_SHADERS").is_none() {
+        return;
+    }
+
+    let shaderc = env::var_os("SHADERC").map_or_else(|| PathBuf::from("shaderc"), PathBuf::from);
+    let include = env::var_os("BGFX_SHADER_INCLUDE").map(PathBuf::from);
+
+    let sources = match fs::read_dir(SOURCE_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    // Build scripts must not write into the source tree, the loader finds the binaries
+    // through `COMPILED_SHADER_DIR` instead
+    let output_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("shaders");
+    println!(
+        "cargo:rustc-env=COMPILED_SHADER_DIR={}",
+        output_dir.display()
+    );
+
+    for entry in sources.filter_map(Result::ok) {
+        let path = entry.path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.ends_with(".sc") && name != "varying.def.sc" => name,
+            _ => continue,
+        };
+
+        // Stage comes from the bgfx naming convention, vs_*.sc, fs_*.sc and cs_*.sc
+        let (stage, profile) = if name.starts_with("vs_") {
+            ("vertex", 0)
+        } else if name.starts_with("fs_") {
+            ("fragment", 1)
+        } else if name.starts_with("cs_") {
+            ("compute", 2)
+        } else {
+            continue;
+        };
+
+        for &(dir, platform, profiles) in TARGETS {
+            // The Direct3D compiler is only available on Windows hosts
+            if dir == "dx11" && !cfg!(windows) {
+                continue;
+            }
+
+            let output = output_dir
+                .join(dir)
+                .join(name.trim_end_matches(".sc"))
+                .with_extension("bin");
+            fs::create_dir_all(output.parent().unwrap()).unwrap();
+
+            let mut command = Command::new(&shaderc);
+            command
+                .arg("-f")
+                .arg(&path)
+                .arg("-o")
+                .arg(&output)
+                .args([
+                    "--type",
+                    stage,
+                    "--platform",
+                    platform,
+                    "-p",
+                    profiles[profile],
+                ])
+                .arg("--varyingdef")
+                .arg(Path::new(SOURCE_DIR).join("varying.def.sc"));
+            if let Some(include) = &include {
+                command.arg("-i").arg(include);
+            }
+
+            let status = command
+                .status()
+                .unwrap_or_else(|error| panic!("could not run {}: {}", shaderc.display(), error));
+            if !status.success() {
+                panic!("shaderc failed to compile {} for {}", path.display(), dir);
+            }
+        }
+    }
+}
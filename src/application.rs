@@ -1,17 +1,45 @@
-use crate::error::InitializationError;
+use crate::config::{self, DebugOption};
+use crate::console::{Console, CvarValue};
+use crate::display::{DisplayMode, MonitorInfo, VideoMode, WindowGeometry};
+use crate::error::{ConsoleError, GlfwError, InitializationError, InputError};
+use crate::event::{Event, EventDispatcher, HandlerId};
+use crate::gamepad::Gamepads;
+use crate::input::InputMap;
+use crate::platform::{self, Resolution};
+use crate::renderer::Renderer;
+use crate::scene::{Scene, SceneStack, Transition};
+use crate::timestep::{FixedTimestep, DEFAULT_MAX_FRAME_TIME, DEFAULT_UPDATE_RATE};
+use crate::view::RenderGraph;
 use bgfx_rs::static_lib::{DebugFlags, Init, PlatformData, RendererType, ResetFlags};
-use glfw::{Action, Glfw, Key, Window, WindowEvent, WindowMode};
-use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use glfw::{Action, Context, Glfw, Key, Modifiers, Window, WindowEvent, WindowMode};
+use raw_window_handle::HasRawWindowHandle;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Receiver;
+use std::sync::Mutex;
+use std::time::Duration;
 
 pub type EventStream = Receiver<(f64, WindowEvent)>;
 
+/// Setting this to anything other than `0` or `false` forces headless mode
+pub const HEADLESS_ENV: &str = "GLFW_TEST_HEADLESS";
+/// Stops the application after the given number of frames
+pub const FRAME_LIMIT_ENV: &str = "GLFW_TEST_FRAMES";
+
 pub struct WindowMetadata<'a> {
     title: &'a str,
     width: u32,
     height: u32,
     mode: WindowMode<'a>,
     debug_flags: DebugFlags,
+    headless: bool,
+    frame_limit: Option<u64>,
+    renderers: Vec<RendererType>,
+    update_rate: u32,
+    max_frame_time: Duration,
+    reset_flags: ResetFlags,
+    display_mode: DisplayMode,
+    monitor: usize,
 }
 
 impl<'a> WindowMetadata<'a> {
@@ -28,140 +56,748 @@ impl<'a> WindowMetadata<'a> {
             height,
             mode,
             debug_flags,
+            headless: headless_from_env(),
+            frame_limit: frame_limit_from_env(),
+            renderers: default_renderers(),
+            update_rate: DEFAULT_UPDATE_RATE,
+            max_frame_time: DEFAULT_MAX_FRAME_TIME,
+            reset_flags: ResetFlags::VSYNC,
+            display_mode: DisplayMode::Windowed,
+            monitor: 0,
         }
     }
+
+    /// Flags passed to bgfx on init and whenever the backbuffer is reset, such as vsync and MSAA
+    pub fn with_reset_flags(mut self, reset_flags: ResetFlags) -> Self {
+        self.reset_flags = reset_flags;
+        self
+    }
+
+    pub fn with_debug_flags(mut self, debug_flags: DebugFlags) -> Self {
+        self.debug_flags = debug_flags;
+        self
+    }
+
+    /// Switch to `display_mode` once the window is created, overriding the `WindowMode`
+    pub fn with_display_mode(mut self, display_mode: DisplayMode) -> Self {
+        self.display_mode = display_mode;
+        self
+    }
+
+    /// Index into `Application::monitors` used for fullscreen and borderless modes
+    pub fn with_monitor(mut self, monitor: usize) -> Self {
+        self.monitor = monitor;
+        self
+    }
+
+    /// Number of fixed updates per second driven by `Application::run`
+    pub fn with_update_rate(mut self, update_rate: u32) -> Self {
+        self.update_rate = update_rate;
+        self
+    }
+
+    /// Upper bound on the frame time fed to the update accumulator
+    pub fn with_max_frame_time(mut self, max_frame_time: Duration) -> Self {
+        self.max_frame_time = max_frame_time;
+        self
+    }
+
+    /// Backends to try during `Application::init`, in order of preference
+    pub fn with_renderers(mut self, renderers: impl IntoIterator<Item = RendererType>) -> Self {
+        self.renderers = renderers.into_iter().collect();
+        self
+    }
+
+    /// Run without a window or GPU, rendering through `RendererType::Noop`
+    pub fn with_headless(mut self, headless: bool) -> Self {
+        self.headless = headless;
+        self
+    }
+
+    /// Close the application once `frame_limit` frames have been submitted
+    pub fn with_frame_limit(mut self, frame_limit: Option<u64>) -> Self {
+        self.frame_limit = frame_limit;
+        self
+    }
 }
 
-/// Wrapper around a glfw window and EventStream for providing initialization abstractions
-pub struct Application {
+fn default_renderers() -> Vec<RendererType> {
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    return vec![RendererType::Vulkan, RendererType::OpenGL];
+    #[cfg(target_os = "macos")]
+    return vec![RendererType::Metal, RendererType::OpenGL];
+}
+
+fn headless_from_env() -> bool {
+    std::env::var(HEADLESS_ENV).map_or(false, |value| parse_env_flag(&value))
+}
+
+/// Environment switches are on for any value other than empty, `0` or `false`
+pub(crate) fn parse_env_flag(value: &str) -> bool {
+    let value = value.trim();
+    !(value.is_empty() || value == "0" || value.eq_ignore_ascii_case("false"))
+}
+
+fn frame_limit_from_env() -> Option<u64> {
+    std::env::var(FRAME_LIMIT_ENV)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Most recent error reported by glfw, taken when an initialization step fails
+static LAST_GLFW_ERROR: Mutex<Option<GlfwError>> = Mutex::new(None);
+/// Set once the window exists, later errors have no step to fail and are logged instead
+static GLFW_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Alt+Enter, toggling exclusive fullscreen from anywhere in `Application::run`
+fn is_fullscreen_toggle(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Key {
+            key: Key::Enter,
+            action: Action::Press,
+            modifiers,
+            ..
+        } if modifiers.contains(Modifiers::Alt)
+    )
+}
+
+fn record_glfw_error(code: glfw::Error, description: String, _: &()) {
+    let error = GlfwError {
+        code: Some(code),
+        description,
+    };
+    if GLFW_RUNNING.load(Ordering::Relaxed) {
+        eprintln!("glfw error: {}", error);
+    } else if let Ok(mut last) = LAST_GLFW_ERROR.lock() {
+        *last = Some(error);
+    }
+}
+
+fn take_glfw_error() -> Option<GlfwError> {
+    LAST_GLFW_ERROR.lock().ok().and_then(|mut last| last.take())
+}
+
+/// The glfw state backing a windowed application
+struct WindowContext {
     glfw: Glfw,
+    window: Window,
     event_stream: EventStream,
-    pub window: glfw::Window,
-    pub size: (u32, u32),
+}
+
+/// Wrapper around a glfw window and EventStream for providing initialization abstractions
+pub struct Application {
+    // Declared before `context` so bgfx shuts down before the window is destroyed
+    renderer: Option<Renderer>,
+    context: Option<WindowContext>,
+    should_close: bool,
+    frame_count: u64,
+    frame_limit: Option<u64>,
+    renderers: Vec<RendererType>,
+    timestep: FixedTimestep,
+    reset_flags: ResetFlags,
+    dispatcher: EventDispatcher,
+    scenes: SceneStack,
+    pub input: InputMap,
+    /// Drop-down developer console, drawn over every scene while open
+    pub console: Console,
+    /// Named views, resized with the backbuffer and applied before scenes render
+    pub views: RenderGraph,
+    gamepads: Gamepads,
+    display_mode: DisplayMode,
+    monitor: usize,
+    fullscreen_video_mode: Option<VideoMode>,
+    windowed_geometry: Option<WindowGeometry>,
+    pub size: Resolution,
     pub debug_flags: DebugFlags,
 }
 
 impl Application {
     fn new(
-        glfw: Glfw,
-        event_stream: EventStream,
-        window: Window,
-        size: (u32, u32),
+        context: Option<WindowContext>,
+        size: Resolution,
+        frame_limit: Option<u64>,
+        renderers: Vec<RendererType>,
+        timestep: FixedTimestep,
+        reset_flags: ResetFlags,
         debug_flags: DebugFlags,
     ) -> Self {
-        Self {
-            glfw,
-            window,
+        let mut application = Self {
+            context,
+            should_close: false,
+            frame_count: 0,
+            frame_limit,
+            renderers,
+            renderer: None,
+            timestep,
+            reset_flags,
+            dispatcher: EventDispatcher::new(),
+            scenes: SceneStack::new(),
+            input: InputMap::new(),
+            console: Console::new(),
+            views: RenderGraph::new(),
+            gamepads: Gamepads::new(),
+            display_mode: DisplayMode::Windowed,
+            monitor: 0,
+            fullscreen_video_mode: None,
+            windowed_geometry: None,
             size,
-            event_stream,
             debug_flags,
-        }
+        };
+        application.register_console_commands();
+        application
+    }
+
+    fn register_console_commands(&mut self) {
+        let vsync = self.reset_flags.contains(ResetFlags::VSYNC);
+        self.console.register_cvar(
+            "vsync",
+            "wait for vertical sync",
+            CvarValue::Bool(vsync),
+            |app, value| {
+                let mut reset_flags = app.reset_flags;
+                reset_flags.set(ResetFlags::VSYNC, value.as_bool().unwrap_or(true));
+                app.set_reset_flags(reset_flags);
+            },
+        );
+        self.console.register_command(
+            "debug",
+            "toggle a bgfx overlay, text, stats, wireframe or profiler",
+            |app, args| {
+                let option = match args {
+                    [option] => config::parse_enum::<DebugOption>(option),
+                    _ => None,
+                }
+                .ok_or_else(|| {
+                    ConsoleError::Usage("debug <text|stats|wireframe|profiler>".to_string())
+                })?;
+
+                let mut debug_flags = app.debug_flags;
+                debug_flags.toggle(option.flags());
+                app.set_debug_flags(debug_flags);
+                Ok(())
+            },
+        );
     }
 
     pub fn try_new(metadata: WindowMetadata<'_>) -> Result<Self, InitializationError> {
-        let glfw = match glfw::init(glfw::FAIL_ON_ERRORS) {
+        let size = Resolution::new(metadata.width, metadata.height);
+        let timestep =
+            FixedTimestep::new(metadata.update_rate).with_max_frame_time(metadata.max_frame_time);
+
+        if metadata.headless {
+            let mut application = Self::new(
+                None,
+                size,
+                metadata.frame_limit,
+                vec![RendererType::Noop],
+                timestep,
+                metadata.reset_flags,
+                metadata.debug_flags,
+            );
+            application.display_mode = metadata.display_mode;
+            application.monitor = metadata.monitor;
+            return Ok(application);
+        }
+
+        // Errors left over from an earlier attempt would be blamed on this one
+        GLFW_RUNNING.store(false, Ordering::Relaxed);
+        take_glfw_error();
+
+        let callback = glfw::Callback {
+            f: record_glfw_error as fn(glfw::Error, String, &()),
+            data: (),
+        };
+        let mut glfw = match glfw::init(Some(callback)) {
             Ok(glfw) => glfw,
-            Err(_) => return Err(InitializationError::Glfw),
+            Err(error) => {
+                let error = take_glfw_error().unwrap_or_else(|| GlfwError {
+                    code: None,
+                    description: format!("{:?}", error),
+                });
+                return Err(InitializationError::Glfw(error));
+            }
         };
 
-        match glfw.create_window(
+        take_glfw_error();
+        let created = glfw.create_window(
             metadata.width,
             metadata.height,
             metadata.title,
             metadata.mode,
-        ) {
-            Some((window, event_stream)) => Ok(Self::new(
-                glfw,
-                event_stream,
-                window,
-                (metadata.width, metadata.height),
-                metadata.debug_flags,
-            )),
-            None => Err(InitializationError::Window),
+        );
+
+        match created {
+            Some((mut window, event_stream)) => {
+                GLFW_RUNNING.store(true, Ordering::Relaxed);
+                window.make_current();
+                window.set_all_polling(true);
+
+                let mut application = Self::new(
+                    Some(WindowContext {
+                        glfw,
+                        window,
+                        event_stream,
+                    }),
+                    size,
+                    metadata.frame_limit,
+                    metadata.renderers,
+                    timestep,
+                    metadata.reset_flags,
+                    metadata.debug_flags,
+                );
+                application.monitor = metadata.monitor;
+                if metadata.display_mode != DisplayMode::Windowed {
+                    application.set_display_mode(metadata.display_mode);
+                }
+
+                Ok(application)
+            }
+            None => Err(InitializationError::Window {
+                width: metadata.width,
+                height: metadata.height,
+                source: take_glfw_error(),
+            }),
         }
     }
 
+    /// Initialize bgfx with the first preferred backend that succeeds. bgfx shuts down
+    /// when the application is dropped.
     pub fn init(&mut self) -> Result<(), InitializationError> {
+        if self.renderer.is_some() {
+            return Err(InitializationError::AlreadyInitialized);
+        }
+
         let mut init = Init::new();
-        init.type_r = self.get_render_type();
-        init.resolution.height = self.size.0;
-        init.resolution.width = self.size.1;
-        init.resolution.reset = ResetFlags::VSYNC.bits(); // enable vsync
-        init.platform_data = self.get_platform_data();
-
-        if !bgfx_rs::static_lib::init(&init) {
-            return Err(InitializationError::Bgfx);
-        };
+        self.size.apply(&mut init);
+        init.resolution.reset = self.reset_flags.bits();
+        init.platform_data = self.get_platform_data()?;
 
+        let renderer = Renderer::init(&mut init, &self.renderers, self.window_handle_kind())?;
+        self.views.resize(self.size);
+        self.renderer = Some(renderer);
         Ok(())
     }
 
-    /// Base event loop
-    pub fn run(
-        &mut self,
-        // executor: Box<dyn FnOnce(&mut Application) -> crate::error::Result<()>>,
-        executor: impl FnOnce(&mut Application) -> crate::error::Result<()>,
-    ) -> crate::error::Result<()> {
-        executor(self)
-    }
-
-    pub fn handle_events(&mut self) {
-        self.glfw.poll_events();
-        glfw::flush_messages(&self.event_stream).for_each(|(_, event)| {
-            println!("{:?}", event);
-            if let glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) = event {
-                self.window.set_should_close(true);
+    /// The bgfx context, `None` before `Application::init`
+    pub fn renderer(&self) -> Option<&Renderer> {
+        self.renderer.as_ref()
+    }
+
+    pub fn reset_flags(&self) -> ResetFlags {
+        self.reset_flags
+    }
+
+    /// Change the reset flags, such as vsync or MSAA, and reset the backbuffer with them
+    pub fn set_reset_flags(&mut self, reset_flags: ResetFlags) {
+        self.reset_flags = reset_flags;
+        // Keep the cvar in sync when the flags change outside the console
+        let vsync = reset_flags.contains(ResetFlags::VSYNC);
+        self.console.set_cvar("vsync", CvarValue::Bool(vsync));
+        self.reset_backbuffer();
+    }
+
+    /// Change the enabled debug overlays, applied immediately once bgfx is running
+    pub fn set_debug_flags(&mut self, debug_flags: DebugFlags) {
+        self.debug_flags = debug_flags;
+        if let Some(renderer) = &self.renderer {
+            renderer.set_debug(debug_flags);
+        }
+    }
+
+    /// Copy the settings that can change while running, the vsync cvar, display mode and
+    /// monitor, into `config`
+    pub fn store_settings(&self, config: &mut config::Config) {
+        config.renderer.vsync = self.reset_flags.contains(ResetFlags::VSYNC);
+        config.window.mode = self.display_mode;
+        config.window.monitor = self.monitor;
+    }
+
+    /// The backend bgfx was initialized with, `None` before `Application::init`
+    pub fn renderer_type(&self) -> Option<RendererType> {
+        self.renderer.as_ref().map(Renderer::renderer_type)
+    }
+
+    /// Base event loop, running `scene` on top of any scenes already pushed and every
+    /// scene it transitions to.
+    ///
+    /// The top scene updates at a fixed rate with the step in seconds and every visible
+    /// scene renders once per frame with how far the frame lies between the previous and
+    /// next update. Headless applications advance exactly one step per frame so runs are
+    /// reproducible.
+    pub fn run(&mut self, scene: impl Scene + 'static) -> crate::error::Result<()> {
+        self.push_scene(Box::new(scene))?;
+
+        self.timestep.reset();
+
+        while !self.should_close() && !self.scenes.is_empty() {
+            for event in self.poll_events() {
+                // Checked first so neither the console nor a scene using Enter can swallow it
+                if is_fullscreen_toggle(&event) {
+                    self.toggle_fullscreen();
+                    continue;
+                }
+                if Console::handle_event(self, &event) {
+                    continue;
+                }
+                self.input.handle_event(&event);
+                if let Event::Resized { .. } = event {
+                    self.reset_backbuffer();
+                }
+                if self.dispatch_event(&event)
+                    || self.with_scenes(|scenes, app| Ok(scenes.handle_event(app, &event)))?
+                {
+                    continue;
+                }
+
+                if event.is_key_press(Key::Escape) {
+                    self.set_should_close(true);
+                }
             }
-        });
+            let updates = match self.is_headless() {
+                true => self.timestep.advance_step(),
+                false => self.timestep.tick(),
+            };
+            // Input edges advance with the fixed updates, a frame without an update keeps
+            // its presses for the next one
+            for _ in 0..updates {
+                self.input.update();
+                self.with_scenes(|scenes, app| scenes.update(app, step.as_secs_f64()))?;
+            }
+
+            if let Some(renderer) = &self.renderer {
+                self.views.apply(renderer);
+            }
+            let alpha = self.timestep.alpha();
+            self.with_scenes(|scenes, app| scenes.render(app, alpha))?;
+            if let Some(renderer) = &self.renderer {
+                self.console.render(renderer, self.size);
+            }
+            self.frame();
+        }
+
+        self.with_scenes(|scenes, app| scenes.clear(app))
     }
 
-    fn get_render_type(&self) -> RendererType {
-        #[cfg(any(target_os = "linux", target_os = "windows"))]
-        return RendererType::Vulkan;
-        #[cfg(target_os = "macos")]
-        return RendererType::Metal;
-    }
-
-    fn get_platform_data(&self) -> PlatformData {
-        let mut pd = PlatformData::new();
-
-        match self.window.raw_window_handle() {
-            #[cfg(any(
-                target_os = "linux",
-                target_os = "dragonfly",
-                target_os = "freebsd",
-                target_os = "netbsd",
-                target_os = "openbsd"
-            ))]
-            RawWindowHandle::Xlib(data) => {
-                use std::ffi::c_void;
-                pd.nwh = data.window as *mut c_void;
-                pd.ndt = data.display as *mut c_void;
+    /// Pause the current scene and enter `scene` on top of it. Requested from inside a
+    /// scene, the change happens once that scene's callback returns.
+    pub fn push_scene(&mut self, scene: Box<dyn Scene>) -> crate::error::Result<()> {
+        self.change_scene(Transition::Push(scene))
+    }
+
+    /// Leave the current scene and resume the one below it
+    pub fn pop_scene(&mut self) -> crate::error::Result<()> {
+        self.change_scene(Transition::Pop)
+    }
+
+    /// Leave the current scene and enter `scene` in its place
+    pub fn replace_scene(&mut self, scene: Box<dyn Scene>) -> crate::error::Result<()> {
+        self.change_scene(Transition::Replace(scene))
+    }
+
+    fn change_scene(&mut self, transition: Transition) -> crate::error::Result<()> {
+        if self.scenes.is_detached() {
+            self.scenes.queue(transition);
+            return Ok(());
+        }
+        self.with_scenes(|scenes, app| scenes.apply(app, transition))
+    }
+
+    /// Run `f` with the scenes taken out of the application so they can borrow it, then
+    /// apply the transitions requested through `push_scene` and friends meanwhile
+    fn with_scenes<T>(
+        &mut self,
+        f: impl FnOnce(&mut SceneStack, &mut Application) -> crate::error::Result<T>,
+    ) -> crate::error::Result<T> {
+        let mut scenes = self.scenes.take();
+        let result = f(&mut scenes, self);
+        self.scenes.restore(scenes);
+        let value = result?;
+
+        while let Some(transition) = self.scenes.next_pending() {
+            self.with_scenes(|scenes, app| scenes.apply(app, transition))?;
+        }
+        Ok(value)
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.context.is_none()
+    }
+
+    /// The underlying glfw window, `None` when running headless
+    pub fn window(&self) -> Option<&Window> {
+        self.context.as_ref().map(|context| &context.window)
+    }
+
+    pub fn window_mut(&mut self) -> Option<&mut Window> {
+        self.context.as_mut().map(|context| &mut context.window)
+    }
+
+    pub fn should_close(&self) -> bool {
+        let window_closed = match &self.context {
+            Some(context) => context.window.should_close(),
+            None => false,
+        };
+        let limit_reached = matches!(self.frame_limit, Some(limit) if self.frame_count >= limit);
+
+        self.should_close || window_closed || limit_reached
+    }
+
+    pub fn set_should_close(&mut self, value: bool) {
+        self.should_close = value;
+        if let Some(window) = self.window_mut() {
+            window.set_should_close(value);
+        }
+    }
+
+    /// Size of the drawable surface, in headless mode this is the requested size
+    pub fn framebuffer_size(&self) -> Resolution {
+        match &self.context {
+            Some(context) => {
+                let (width, height) = context.window.get_framebuffer_size();
+                Resolution::new(width as u32, height as u32)
             }
-            #[cfg(any(
-                target_os = "linux",
-                target_os = "dragonfly",
-                target_os = "freebsd",
-                target_os = "netbsd",
-                target_os = "openbsd"
-            ))]
-            RawWindowHandle::Wayland(data) => {
-                pd.ndt = data.surface; // same as window, on wayland there ins't a concept of windows
-                pd.nwh = data.display;
+            None => self.size,
+        }
+    }
+
+    /// Number of frames submitted through `Application::frame`
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Submit the current frame to bgfx
+    pub fn frame(&mut self) {
+        if let Some(renderer) = &self.renderer {
+            renderer.frame();
+        }
+        self.frame_count += 1;
+    }
+
+    /// Every connected monitor, the primary monitor first
+    pub fn monitors(&mut self) -> Vec<MonitorInfo> {
+        match self.context.as_mut() {
+            Some(context) => context.glfw.with_connected_monitors(|_, monitors| {
+                monitors
+                    .iter()
+                    .enumerate()
+                    .map(|(index, monitor)| MonitorInfo::new(index, monitor))
+                    .collect()
+            }),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn display_mode(&self) -> DisplayMode {
+        self.display_mode
+    }
+
+    /// Index into `Application::monitors` used for fullscreen and borderless modes
+    pub fn monitor(&self) -> usize {
+        self.monitor
+    }
+
+    /// Move fullscreen and borderless windows to another monitor, falling back to the
+    /// primary monitor if `monitor` isn't connected
+    pub fn set_monitor(&mut self, monitor: usize) {
+        self.monitor = monitor;
+        if self.display_mode != DisplayMode::Windowed {
+            self.set_display_mode(self.display_mode);
+        }
+    }
+
+    /// Resolution and refresh rate for exclusive fullscreen, `None` keeps the desktop mode
+    pub fn set_fullscreen_video_mode(&mut self, video_mode: Option<VideoMode>) {
+        self.fullscreen_video_mode = video_mode;
+        if self.display_mode == DisplayMode::Fullscreen {
+            self.set_display_mode(DisplayMode::Fullscreen);
+        }
+    }
+
+    /// Switch between windowed, borderless and exclusive fullscreen, resetting the
+    /// backbuffer to the new size. The windowed position and size are restored when
+    /// returning to `DisplayMode::Windowed`.
+    pub fn set_display_mode(&mut self, mode: DisplayMode) {
+        let context = match self.context.as_mut() {
+            Some(context) => context,
+            None => {
+                self.display_mode = mode;
+                return;
             }
+        };
 
-            #[cfg(target_os = "macos")]
-            RawWindowHandle::MacOS(data) => {
-                pd.nwh = data.ns_window;
+        if self.display_mode == DisplayMode::Windowed && mode != DisplayMode::Windowed {
+            let (x, y) = context.window.get_pos();
+            let (width, height) = context.window.get_size();
+            self.windowed_geometry = Some(WindowGeometry {
+                x,
+                y,
+                width: width as u32,
+                height: height as u32,
+            });
+        }
+
+        let index = self.monitor;
+        let video_mode = self.fullscreen_video_mode;
+        let windowed = self.windowed_geometry;
+        let WindowContext { glfw, window, .. } = context;
+
+        self.display_mode = glfw.with_connected_monitors(|_, monitors| {
+            let monitor = monitors.get(index).or_else(|| monitors.first());
+            let current = monitor.and_then(|monitor| monitor.get_video_mode());
+
+            match (mode, monitor, current) {
+                (DisplayMode::Fullscreen, Some(monitor), Some(current)) => {
+                    let video_mode = video_mode.unwrap_or_else(|| VideoMode::from(current));
+                    window.set_monitor(
+                        WindowMode::FullScreen(monitor),
+                        0,
+                        0,
+                        video_mode.width,
+                        video_mode.height,
+                        Some(video_mode.refresh_rate),
+                    );
+                    DisplayMode::Fullscreen
+                }
+                (DisplayMode::Borderless, Some(monitor), Some(current)) => {
+                    let (x, y) = monitor.get_pos();
+                    window.set_decorated(false);
+                    window.set_monitor(
+                        WindowMode::Windowed,
+                        x,
+                        y,
+                        current.width,
+                        current.height,
+                        None,
+                    );
+                    DisplayMode::Borderless
+                }
+                // Without a usable monitor every mode falls back to windowed
+                _ => {
+                    let (x, y) = window.get_pos();
+                    let (width, height) = window.get_size();
+                    let geometry = windowed.unwrap_or(WindowGeometry {
+                        x,
+                        y,
+                        width: width as u32,
+                        height: height as u32,
+                    });
+                    window.set_decorated(true);
+                    window.set_monitor(
+                        WindowMode::Windowed,
+                        geometry.x,
+                        geometry.y,
+                        geometry.width,
+                        geometry.height,
+                        None,
+                    );
+                    DisplayMode::Windowed
+                }
             }
-            #[cfg(target_os = "windows")]
-            RawWindowHandle::Win32(data) => {
-                pd.nwh = data.hwnd;
+        });
+
+        self.reset_backbuffer();
+    }
+
+    /// Toggle between exclusive fullscreen and windowed, bound to Alt+Enter in `run`
+    pub fn toggle_fullscreen(&mut self) {
+        match self.display_mode {
+            DisplayMode::Windowed => self.set_display_mode(DisplayMode::Fullscreen),
+            _ => self.set_display_mode(DisplayMode::Windowed),
+        }
+    }
+
+    /// Resize the bgfx backbuffer to the current framebuffer size
+    pub fn reset_backbuffer(&mut self) {
+        let size = self.framebuffer_size();
+        if let Some(renderer) = &self.renderer {
+            renderer.reset(size, self.reset_flags);
+        }
+        self.size = size;
+        self.views.resize(size);
+    }
+
+    /// Register a handler that sees every event before the active scene does
+    pub fn add_event_handler(
+        &mut self,
+        handler: impl FnMut(&mut Application, &Event) -> bool + 'static,
+    ) -> HandlerId {
+        self.dispatcher.add_handler(handler)
+    }
+
+    pub fn remove_event_handler(&mut self, id: HandlerId) -> bool {
+        self.dispatcher.remove_handler(id)
+    }
+
+    /// Poll glfw and drain the window and gamepad events received since the last call
+    pub fn poll_events(&mut self) -> Vec<Event> {
+        let context = match self.context.as_mut() {
+            Some(context) => context,
+            None => return Vec::new(),
+        };
+
+        context.glfw.poll_events();
+        let mut events: Vec<Event> = glfw::flush_messages(&context.event_stream)
+            .filter_map(|(_, event)| Event::from_window_event(event))
+            .collect();
+        events.extend(self.gamepads.poll(&context.glfw));
+
+        events
+    }
+
+    pub fn gamepads(&self) -> &Gamepads {
+        &self.gamepads
+    }
+
+    pub fn set_gamepad_deadzone(&mut self, deadzone: f32) {
+        self.gamepads.set_deadzone(deadzone);
+    }
+
+    /// Add SDL gamecontrollerdb style mapping strings, one mapping per line. Headless
+    /// applications have no glfw to apply them to and report an error.
+    pub fn add_gamepad_mappings(&mut self, mappings: &str) -> Result<(), InputError> {
+        match &self.context {
+            None => Err(InputError::HeadlessGamepadMappings),
+            Some(context) if !context.glfw.update_gamepad_mappings(mappings) => {
+                Err(InputError::GamepadMappings)
             }
-            _ => panic!("Unsupported Window Manager"),
+            Some(_) => Ok(()),
         }
+    }
+
+    /// Load a gamecontrollerdb.txt file, see `Application::add_gamepad_mappings`
+    pub fn load_gamepad_mappings(&mut self, path: impl AsRef<Path>) -> Result<(), InputError> {
+        let path = path.as_ref();
+        let mappings = std::fs::read_to_string(path).map_err(|source| InputError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
 
-        pd
+        self.add_gamepad_mappings(&mappings)
+    }
+
+    /// Deliver `event` to the registered handlers, returning whether one consumed it
+    fn dispatch_event(&mut self, event: &Event) -> bool {
+        let mut dispatcher = self.dispatcher.take();
+        let consumed = dispatcher.dispatch(self, event);
+        self.dispatcher.restore(dispatcher);
+        consumed
+    }
+
+    /// Name of the raw window handle variant, for error reports
+    fn window_handle_kind(&self) -> &'static str {
+        match self.window() {
+            Some(window) => platform::handle_kind(&window.raw_window_handle()),
+            None => "headless",
+        }
+    }
+
+    fn get_platform_data(&self) -> Result<PlatformData, InitializationError> {
+        match self.window() {
+            Some(window) => platform::native_handles(window.raw_window_handle())
+                .map(|handles| handles.platform_data()),
+            None => Ok(PlatformData::new()),
+        }
     }
 }
 
@@ -170,3 +806,49 @@ impl AsMut<Application> for Application {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enter(action: Action, modifiers: Modifiers) -> Event {
+        Event::Key {
+            key: Key::Enter,
+            scancode: 0,
+            action,
+            modifiers,
+        }
+    }
+
+    #[test]
+    fn alt_enter_toggles_fullscreen() {
+        assert!(is_fullscreen_toggle(&enter(Action::Press, Modifiers::Alt)));
+        assert!(is_fullscreen_toggle(&enter(
+            Action::Press,
+            Modifiers::Alt | Modifiers::Shift
+        )));
+    }
+
+    #[test]
+    fn plain_or_repeated_enter_is_left_alone() {
+        // Plain Enter still reaches the console to submit its input line
+        assert!(!is_fullscreen_toggle(&enter(
+            Action::Press,
+            Modifiers::empty()
+        )));
+        assert!(!is_fullscreen_toggle(&enter(
+            Action::Repeat,
+            Modifiers::Alt
+        )));
+        assert!(!is_fullscreen_toggle(&enter(
+            Action::Release,
+            Modifiers::Alt
+        )));
+        assert!(!is_fullscreen_toggle(&Event::Key {
+            key: Key::KpEnter,
+            scancode: 0,
+            action: Action::Press,
+            modifiers: Modifiers::Control,
+        }));
+    }
+}
@@ -0,0 +1,430 @@
+use crate::platform::Resolution;
+use crate::renderer::Renderer;
+use crate::view::ViewId;
+use glam::{Mat4, Vec2, Vec3, Vec4, Vec4Swizzles};
+use std::f32::consts::FRAC_PI_2;
+
+/// Orthographic camera in world units with y pointing down, matching tile coordinates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera2D {
+    /// World position shown at the center of the viewport
+    pub position: Vec2,
+    zoom: f32,
+    min_zoom: f32,
+    max_zoom: f32,
+    viewport: Vec2,
+    bounds: Option<(Vec2, Vec2)>,
+}
+
+impl Camera2D {
+    pub fn new(viewport: Resolution) -> Self {
+        Self {
+            position: Vec2::ZERO,
+            zoom: 1.0,
+            min_zoom: 0.1,
+            max_zoom: 10.0,
+            viewport: viewport.as_vec2(),
+            bounds: None,
+        }
+    }
+
+    /// Keep the visible area inside the world rectangle `min..max`
+    pub fn with_bounds(mut self, min: Vec2, max: Vec2) -> Self {
+        self.set_bounds(Some((min, max)));
+        self
+    }
+
+    pub fn with_zoom_range(mut self, min_zoom: f32, max_zoom: f32) -> Self {
+        self.min_zoom = min_zoom;
+        self.max_zoom = max_zoom.max(min_zoom);
+        self.set_zoom(self.zoom);
+        self
+    }
+
+    /// Screen pixels per world unit
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(self.min_zoom, self.max_zoom);
+        self.clamp();
+    }
+
+    /// Multiply the zoom by `factor` while keeping the world point under `anchor`, in
+    /// screen pixels, fixed on screen
+    pub fn zoom_at(&mut self, factor: f32, anchor: Vec2) {
+        let before = self.screen_to_world(anchor);
+        self.zoom = (self.zoom * factor).clamp(self.min_zoom, self.max_zoom);
+        let after = self.screen_to_world(anchor);
+        self.position += before - after;
+        self.clamp();
+    }
+
+    /// Move by `delta` world units
+    pub fn pan(&mut self, delta: Vec2) {
+        self.position += delta;
+        self.clamp();
+    }
+
+    /// Move by `delta` screen pixels, as when dragging with the mouse
+    pub fn pan_screen(&mut self, delta: Vec2) {
+        self.pan(-delta / self.zoom);
+    }
+
+    pub fn set_position(&mut self, position: Vec2) {
+        self.position = position;
+        self.clamp();
+    }
+
+    pub fn set_bounds(&mut self, bounds: Option<(Vec2, Vec2)>) {
+        self.bounds = bounds.map(|(min, max)| (min.min(max), min.max(max)));
+        self.clamp();
+    }
+
+    pub fn viewport(&self) -> Resolution {
+        Resolution::new(self.viewport.x as u32, self.viewport.y as u32)
+    }
+
+    pub fn set_viewport(&mut self, viewport: Resolution) {
+        self.viewport = viewport.as_vec2();
+        self.clamp();
+    }
+
+    /// World rectangle `(min, max)` currently visible, as expected by `TileRenderer::draw`
+    pub fn visible_area(&self) -> (Vec2, Vec2) {
+        let half = self.half_extent();
+        (self.position - half, self.position + half)
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::from_translation(-self.position.extend(0.0))
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        let half = self.half_extent();
+        Mat4::orthographic_rh(-half.x, half.x, half.y, -half.y, -1.0, 1.0)
+    }
+
+    pub fn screen_to_world(&self, screen: Vec2) -> Vec2 {
+        self.position + (screen - self.viewport * 0.5) / self.zoom
+    }
+
+    pub fn world_to_screen(&self, world: Vec2) -> Vec2 {
+        (world - self.position) * self.zoom + self.viewport * 0.5
+    }
+
+    /// Set the view and projection of `view` for this frame
+    pub fn apply(&self, renderer: &Renderer, view: ViewId) {
+        renderer.set_view_transform(view, &self.view_matrix(), &self.projection_matrix());
+    }
+
+    fn half_extent(&self) -> Vec2 {
+        self.viewport * 0.5 / self.zoom
+    }
+
+    /// Keep the visible area inside the bounds, centering on axes where it's larger
+    fn clamp(&mut self) {
+        let (min, max) = match self.bounds {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let half = self.half_extent();
+        let center = (min + max) * 0.5;
+
+        for axis in 0..2 {
+            let (low, high) = (min[axis] + half[axis], max[axis] - half[axis]);
+            self.position[axis] = match low >= high {
+                true => center[axis],
+                false => self.position[axis].clamp(low, high),
+            };
+        }
+    }
+}
+
+/// Perspective camera looking along `yaw` and `pitch`, with y up
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera3D {
+    pub position: Vec3,
+    /// Rotation around the y axis in radians, `0` looks down +z
+    pub yaw: f32,
+    /// Rotation above the horizon in radians
+    pub pitch: f32,
+    /// Vertical field of view in radians
+    pub fov_y: f32,
+    pub near: f32,
+    pub far: f32,
+    viewport: Vec2,
+}
+
+impl Camera3D {
+    pub fn new(viewport: Resolution) -> Self {
+        Self {
+            position: Vec3::ZERO,
+            yaw: 0.0,
+            pitch: 0.0,
+            fov_y: 60f32.to_radians(),
+            near: 0.1,
+            far: 1000.0,
+            viewport: viewport.as_vec2(),
+        }
+    }
+
+    pub fn set_viewport(&mut self, viewport: Resolution) {
+        self.viewport = viewport.as_vec2();
+    }
+
+    /// Point the camera at `target` from its current position
+    pub fn look_at(&mut self, target: Vec3) {
+        let direction = (target - self.position).normalize_or_zero();
+        if direction != Vec3::ZERO {
+            self.yaw = direction.x.atan2(direction.z);
+            self.pitch = direction.y.asin();
+        }
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        Vec3::new(sin_yaw * cos_pitch, sin_pitch, cos_yaw * cos_pitch)
+    }
+
+    pub fn right(&self) -> Vec3 {
+        Vec3::Y.cross(self.forward()).normalize_or_zero()
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.forward().cross(self.right())
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.viewport.x / self.viewport.y.max(1.0)
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::look_at_lh(self.position, self.position + self.forward(), Vec3::Y)
+    }
+
+    /// Projection with depth in `0..1`, or `-1..1` when `homogeneous_depth` is set as
+    /// OpenGL expects
+    pub fn projection_matrix(&self, homogeneous_depth: bool) -> Mat4 {
+        let aspect_ratio = self.aspect_ratio();
+        let projection = Mat4::perspective_lh(self.fov_y, aspect_ratio, self.near, self.far);
+        match homogeneous_depth {
+            // Remap z from 0..1 to -1..1
+            true => {
+                Mat4::from_cols(
+                    Vec4::X,
+                    Vec4::Y,
+                    Vec4::Z * 2.0,
+                    Vec4::new(0.0, 0.0, -1.0, 1.0),
+                ) * projection
+            }
+            false => projection,
+        }
+    }
+
+    /// Ray `(origin, direction)` through a screen pixel, starting on the near plane
+    pub fn screen_to_ray(&self, screen: Vec2) -> (Vec3, Vec3) {
+        let ndc = Vec2::new(
+            screen.x / self.viewport.x * 2.0 - 1.0,
+            1.0 - screen.y / self.viewport.y * 2.0,
+        );
+        let inverse = (self.projection_matrix(false) * self.view_matrix()).inverse();
+        let unproject = |depth: f32| {
+            let point = inverse * ndc.extend(depth).extend(1.0);
+            point.xyz() / point.w
+        };
+
+        let near = unproject(0.0);
+        let far = unproject(1.0);
+        (near, (far - near).normalize_or_zero())
+    }
+
+    /// Screen pixel of a world point, `None` when it's behind the camera
+    pub fn world_to_screen(&self, world: Vec3) -> Option<Vec2> {
+        let clip = self.projection_matrix(false) * self.view_matrix() * world.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+
+        let ndc = clip.xy() / clip.w;
+        Some(Vec2::new(
+            (ndc.x + 1.0) * 0.5 * self.viewport.x,
+            (1.0 - ndc.y) * 0.5 * self.viewport.y,
+        ))
+    }
+
+    /// Set the view and projection of `view` for this frame
+    pub fn apply(&self, renderer: &Renderer, view: ViewId) {
+        let projection = self.projection_matrix(renderer.homogeneous_depth());
+        renderer.set_view_transform(view, &self.view_matrix(), &projection);
+    }
+}
+
+// Just short of straight up or down, where the view matrix degenerates
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+/// First person controls: move relative to the view direction and look around
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlyController {
+    /// World units per second
+    pub speed: f32,
+    /// Radians per unit of look input, such as a pixel of mouse movement
+    pub sensitivity: f32,
+}
+
+impl FlyController {
+    pub fn new(speed: f32, sensitivity: f32) -> Self {
+        Self { speed, sensitivity }
+    }
+
+    /// `movement` is `(right, up, forward)` input in `-1..=1`, `look` is the yaw and pitch
+    /// input since the last update
+    pub fn update(&self, camera: &mut Camera3D, movement: Vec3, look: Vec2, dt: f32) {
+        camera.yaw += look.x * self.sensitivity;
+        camera.pitch = (camera.pitch - look.y * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+
+        let velocity =
+            camera.right() * movement.x + Vec3::Y * movement.y + camera.forward() * movement.z;
+        camera.position += velocity.clamp_length_max(1.0) * self.speed * dt;
+    }
+}
+
+/// Rotates the camera around a target point at a fixed distance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Radians per unit of rotate input
+    pub sensitivity: f32,
+}
+
+impl OrbitController {
+    pub fn new(target: Vec3, distance: f32) -> Self {
+        Self {
+            target,
+            distance,
+            yaw: 0.0,
+            pitch: 0.3,
+            min_distance: 1.0,
+            max_distance: 100.0,
+            sensitivity: 0.005,
+        }
+    }
+
+    pub fn rotate(&mut self, delta: Vec2) {
+        self.yaw += delta.x * self.sensitivity;
+        self.pitch = (self.pitch + delta.y * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Move closer for positive `delta`, such as scroll wheel input
+    pub fn zoom(&mut self, delta: f32) {
+        self.distance =
+            (self.distance * 0.9f32.powf(delta)).clamp(self.min_distance, self.max_distance);
+    }
+
+    /// Place `camera` on the orbit, looking at the target
+    pub fn update(&self, camera: &mut Camera3D) {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let offset = Vec3::new(sin_yaw * cos_pitch, sin_pitch, cos_yaw * cos_pitch);
+
+        camera.position = self.target + offset * self.distance;
+        camera.look_at(self.target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_and_world_round_trip_at_any_zoom() {
+        let mut camera = Camera2D::new(Resolution::new(640, 360));
+        camera.set_position(Vec2::new(120.0, -40.0));
+
+        for zoom in [0.25, 1.0, 3.0, 7.5] {
+            camera.set_zoom(zoom);
+            for screen in [Vec2::ZERO, Vec2::new(320.0, 180.0), Vec2::new(17.0, 301.5)] {
+                let world = camera.screen_to_world(screen);
+                assert!(camera.world_to_screen(world).abs_diff_eq(screen, 1e-3));
+            }
+        }
+    }
+
+    #[test]
+    fn viewport_center_shows_the_position() {
+        let mut camera = Camera2D::new(Resolution::new(640, 360));
+        camera.set_position(Vec2::new(8.0, 16.0));
+        camera.set_zoom(2.0);
+        assert_eq!(
+            camera.screen_to_world(Vec2::new(320.0, 180.0)),
+            camera.position
+        );
+        // A world unit is `zoom` pixels
+        assert_eq!(
+            camera.world_to_screen(camera.position + Vec2::X),
+            Vec2::new(322.0, 180.0)
+        );
+    }
+
+    #[test]
+    fn bounds_keep_the_visible_area_inside() {
+        let mut camera = Camera2D::new(Resolution::new(200, 100))
+            .with_bounds(Vec2::ZERO, Vec2::new(1000.0, 500.0));
+
+        camera.set_position(Vec2::new(-50.0, -50.0));
+        assert_eq!(camera.position, Vec2::new(100.0, 50.0));
+        camera.pan(Vec2::new(5000.0, 5000.0));
+        assert_eq!(camera.position, Vec2::new(900.0, 450.0));
+        assert_eq!(
+            camera.visible_area(),
+            (Vec2::new(800.0, 400.0), Vec2::new(1000.0, 500.0))
+        );
+
+        // Zoomed out past the bounds the camera centers on them
+        camera.set_zoom(0.1);
+        assert_eq!(camera.position, Vec2::new(500.0, 250.0));
+    }
+
+    #[test]
+    fn zoom_is_clamped_to_its_range() {
+        let mut camera = Camera2D::new(Resolution::new(200, 100)).with_zoom_range(0.5, 4.0);
+        camera.set_zoom(100.0);
+        assert_eq!(camera.zoom(), 4.0);
+        camera.zoom_at(0.01, Vec2::ZERO);
+        assert_eq!(camera.zoom(), 0.5);
+    }
+
+    #[test]
+    fn ray_through_the_center_points_forward() {
+        let mut camera = Camera3D::new(Resolution::new(800, 600));
+        camera.position = Vec3::new(3.0, 2.0, -5.0);
+        camera.yaw = 0.7;
+        camera.pitch = -0.3;
+
+        let (origin, direction) = camera.screen_to_ray(Vec2::new(400.0, 300.0));
+        assert!(direction.abs_diff_eq(camera.forward(), 1e-3));
+        assert!(origin.abs_diff_eq(camera.position + camera.forward() * camera.near, 1e-3));
+    }
+
+    #[test]
+    fn points_ahead_project_to_the_center_and_behind_are_hidden() {
+        let mut camera = Camera3D::new(Resolution::new(800, 600));
+        camera.position = Vec3::new(1.0, 1.0, 1.0);
+        camera.look_at(Vec3::new(4.0, 0.0, 9.0));
+
+        let ahead = camera.position + camera.forward() * 10.0;
+        let screen = camera.world_to_screen(ahead).unwrap();
+        assert!(screen.abs_diff_eq(Vec2::new(400.0, 300.0), 1e-2));
+        assert_eq!(
+            camera.world_to_screen(camera.position - camera.forward()),
+            None
+        );
+    }
+}
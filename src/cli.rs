@@ -0,0 +1,256 @@
+use crate::config::{self, Backend, Config, DebugOption};
+use crate::display::DisplayMode;
+use crate::error::CliError;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: glfw-test [OPTIONS]
+
+Options:
+  --width <PIXELS>          Window width
+  --height <PIXELS>         Window height
+  --fullscreen              Open in exclusive fullscreen
+  --borderless              Open as a borderless window covering the monitor
+  --monitor <INDEX>         Monitor for fullscreen and borderless, 0 is the primary
+  --renderer <BACKENDS>     Comma separated backends to try in order
+                            (vulkan, opengl, opengles, metal, direct3d11, direct3d12, noop)
+  --debug <FLAGS>           Comma separated debug overlays (text, stats, wireframe, profiler)
+  --headless                Run without a window or GPU
+  --frames <N>              Exit after rendering N frames
+  --config <PATH>           Config file to load instead of config.toml
+  --set <KEY=VALUE>         Override a single config key, such as renderer.vsync=false
+  -h, --help                Print this message
+";
+
+/// Options given on the command line, each overriding the config file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Args {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub display_mode: Option<DisplayMode>,
+    pub monitor: Option<usize>,
+    pub renderers: Option<Vec<Backend>>,
+    pub debug: Option<Vec<DebugOption>>,
+    pub headless: bool,
+    pub frames: Option<u64>,
+    pub config: Option<PathBuf>,
+    pub overrides: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Run(Args),
+    Help,
+}
+
+impl Args {
+    /// Parse the arguments following the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, CliError> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Accept both `--flag value` and `--flag=value`
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| CliError::MissingValue(flag.clone()))
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                "--width" => parsed.width = Some(parse_size(&flag, value()?)?),
+                "--height" => parsed.height = Some(parse_size(&flag, value()?)?),
+                "--monitor" => parsed.monitor = Some(parse_number(&flag, value()?)?),
+                "--frames" => parsed.frames = Some(parse_number(&flag, value()?)?),
+                "--renderer" => parsed.renderers = Some(parse_list(&flag, &value()?)?),
+                "--debug" => parsed.debug = Some(parse_list(&flag, &value()?)?),
+                "--config" => parsed.config = Some(PathBuf::from(value()?)),
+                "--set" => parsed.overrides.push(value()?),
+                "--fullscreen" => parsed.display_mode = Some(DisplayMode::Fullscreen),
+                "--borderless" => parsed.display_mode = Some(DisplayMode::Borderless),
+                "--headless" => parsed.headless = true,
+                _ => return Err(CliError::UnknownArgument(flag)),
+            }
+        }
+
+        Ok(Command::Run(parsed))
+    }
+
+    /// Apply these options on top of `config`, failing if the result doesn't validate
+    pub fn apply(&self, config: &mut Config) -> Result<(), CliError> {
+        for assignment in &self.overrides {
+            config.apply_override(assignment)?;
+        }
+
+        if let Some(width) = self.width {
+            config.window.width = width;
+        }
+        if let Some(height) = self.height {
+            config.window.height = height;
+        }
+        if let Some(renderers) = &self.renderers {
+            config.renderer.backends = renderers.clone();
+        }
+        if let Some(debug) = &self.debug {
+            config.renderer.debug = debug.clone();
+        }
+        if let Some(mode) = self.display_mode {
+            config.window.mode = mode;
+        }
+        if let Some(monitor) = self.monitor {
+            config.window.monitor = monitor;
+        }
+        config.window.headless |= self.headless;
+
+        Ok(config.validate()?)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: String) -> Result<T, CliError> {
+    value.parse().map_err(|_| CliError::InvalidValue {
+        flag: flag.to_string(),
+        value,
+    })
+}
+
+/// Window sizes share the `1..=65535` range enforced by `Config::validate`
+fn parse_size(flag: &str, value: String) -> Result<u32, CliError> {
+    match parse_number(flag, value.clone())? {
+        size @ 1..=0xffff => Ok(size),
+        _ => Err(CliError::InvalidValue {
+            flag: flag.to_string(),
+            value,
+        }),
+    }
+}
+
+fn parse_list<T: for<'de> serde::Deserialize<'de>>(
+    flag: &str,
+    value: &str,
+) -> Result<Vec<T>, CliError> {
+    config::parse_list(value).ok_or_else(|| CliError::InvalidValue {
+        flag: flag.to_string(),
+        value: value.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, CliError> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn run_args(args: &[&str]) -> Args {
+        match parse(args) {
+            Ok(Command::Run(args)) => args,
+            other => panic!("expected run arguments, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_separate_and_inline_values() {
+        let args = run_args(&[
+            "--width",
+            "800",
+            "--height=600",
+            "--renderer",
+            "opengl, noop",
+            "--debug=stats,wireframe",
+            "--borderless",
+            "--frames=3",
+        ]);
+        assert_eq!((args.width, args.height), (Some(800), Some(600)));
+        assert_eq!(args.renderers, Some(vec![Backend::OpenGL, Backend::Noop]));
+        assert_eq!(
+            args.debug,
+            Some(vec![DebugOption::Stats, DebugOption::Wireframe])
+        );
+        assert_eq!(args.display_mode, Some(DisplayMode::Borderless));
+        assert_eq!(args.frames, Some(3));
+    }
+
+    #[test]
+    fn help_wins_over_everything_else() {
+        assert_eq!(parse(&["--width", "800", "-h"]).unwrap(), Command::Help);
+        assert_eq!(parse(&["--help", "--bogus"]).unwrap(), Command::Help);
+    }
+
+    #[test]
+    fn missing_values_are_reported() {
+        assert!(matches!(
+            parse(&["--frames"]),
+            Err(CliError::MissingValue(flag)) if flag == "--frames"
+        ));
+        assert!(matches!(
+            parse(&["--headless", "--set"]),
+            Err(CliError::MissingValue(flag)) if flag == "--set"
+        ));
+    }
+
+    #[test]
+    fn unknown_flags_are_reported() {
+        assert!(matches!(
+            parse(&["--windowed"]),
+            Err(CliError::UnknownArgument(flag)) if flag == "--windowed"
+        ));
+        assert!(matches!(
+            parse(&["800"]),
+            Err(CliError::UnknownArgument(flag)) if flag == "800"
+        ));
+    }
+
+    #[test]
+    fn invalid_values_are_reported() {
+        for args in [
+            ["--width", "wide"],
+            ["--width", "0"],
+            ["--height", "70000"],
+            ["--renderer", "vulkan,glide"],
+            ["--debug", "text,colors"],
+        ] {
+            match parse(&args) {
+                Err(CliError::InvalidValue { flag, value }) => {
+                    assert_eq!([flag.as_str(), value.as_str()], args)
+                }
+                other => panic!("{:?} should be rejected, got {:?}", args, other),
+            }
+        }
+    }
+
+    #[test]
+    fn set_overrides_apply_before_flags() {
+        let args = run_args(&[
+            "--set",
+            "renderer.vsync=false",
+            "--set=window.width=640",
+            "--width",
+            "800",
+        ]);
+        assert_eq!(args.overrides, ["renderer.vsync=false", "window.width=640"]);
+
+        let mut config = Config::default();
+        args.apply(&mut config).unwrap();
+        assert!(!config.renderer.vsync);
+        assert_eq!(config.window.width, 800);
+    }
+
+    #[test]
+    fn bad_overrides_are_cli_errors() {
+        let mut config = Config::default();
+        for assignment in ["window.width=0", "window.colour=red", "vsync"] {
+            let args = run_args(&["--set", assignment]);
+            assert!(matches!(args.apply(&mut config), Err(CliError::Config(_))));
+        }
+        assert_eq!(config, Config::default());
+    }
+}
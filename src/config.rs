@@ -0,0 +1,397 @@
+use crate::application::{self, WindowMetadata};
+use crate::display::DisplayMode;
+use crate::error::ConfigError;
+use bgfx_rs::static_lib::{DebugFlags, RendererType, ResetFlags};
+use glfw::WindowMode;
+use serde::de::value::{Error as ValueError, StrDeserializer};
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const DEFAULT_PATH: &str = "config.toml";
+/// Overrides the location of the config file
+pub const PATH_ENV: &str = "GLFW_TEST_CONFIG";
+
+/// Environment variables named `GLFW_TEST_<FIELD>` override the matching config field
+pub const ENV_PREFIX: &str = "GLFW_TEST_";
+
+/// Every key accepted by `Config::set`
+pub const KEYS: &[&str] = &[
+    "window.title",
+    "window.width",
+    "window.height",
+    "window.mode",
+    "window.monitor",
+    "window.headless",
+    "renderer.vsync",
+    "renderer.msaa",
+    "renderer.backends",
+    "renderer.debug",
+    "renderer.update_rate",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Noop,
+    Direct3D11,
+    Direct3D12,
+    Metal,
+    OpenGL,
+    OpenGLES,
+    Vulkan,
+}
+
+impl Backend {
+    pub fn renderer_type(self) -> RendererType {
+        match self {
+            Backend::Noop => RendererType::Noop,
+            Backend::Direct3D11 => RendererType::Direct3D11,
+            Backend::Direct3D12 => RendererType::Direct3D12,
+            Backend::Metal => RendererType::Metal,
+            Backend::OpenGL => RendererType::OpenGL,
+            Backend::OpenGLES => RendererType::OpenGLES,
+            Backend::Vulkan => RendererType::Vulkan,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DebugOption {
+    Text,
+    Stats,
+    Wireframe,
+    Profiler,
+}
+
+impl DebugOption {
+    pub fn flags(self) -> DebugFlags {
+        match self {
+            DebugOption::Text => DebugFlags::TEXT,
+            DebugOption::Stats => DebugFlags::STATS,
+            DebugOption::Wireframe => DebugFlags::WIREFRAME,
+            DebugOption::Profiler => DebugFlags::PROFILER,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    pub mode: DisplayMode,
+    /// Monitor index for fullscreen and borderless modes, `0` is the primary monitor
+    pub monitor: usize,
+    pub headless: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "Test window".to_string(),
+            width: 1280,
+            height: 720,
+            mode: DisplayMode::Windowed,
+            monitor: 0,
+            headless: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RendererConfig {
+    pub vsync: bool,
+    /// Samples per pixel, `0` or `1` disables multisampling
+    pub msaa: u8,
+    /// Tried in order until one initializes
+    pub backends: Vec<Backend>,
+    pub debug: Vec<DebugOption>,
+    /// Fixed updates per second
+    pub update_rate: u32,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            vsync: true,
+            msaa: 0,
+            #[cfg(target_os = "macos")]
+            backends: vec![Backend::Metal, Backend::OpenGL],
+            #[cfg(not(target_os = "macos"))]
+            backends: vec![Backend::Vulkan, Backend::OpenGL],
+            debug: vec![DebugOption::Text],
+            update_rate: 60,
+        }
+    }
+}
+
+/// Window and renderer settings, loaded from TOML and overridable per key
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub renderer: RendererConfig,
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let config: Config = toml::from_str(&source)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Load `path` if it exists, otherwise start from the defaults
+    pub fn load_or_default(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        match path.as_ref().exists() {
+            true => Self::load(path),
+            false => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        let source = toml::to_string_pretty(self)?;
+        std::fs::write(path, source).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Take the runtime settings that differ between `started` and `current` into this
+    /// config, so environment and command line overrides aren't written back to the file.
+    /// Returns whether anything changed.
+    pub fn merge_changes(&mut self, started: &Config, current: &Config) -> bool {
+        let mut changed = false;
+        if current.renderer.vsync != started.renderer.vsync {
+            changed |= self.renderer.vsync != current.renderer.vsync;
+            self.renderer.vsync = current.renderer.vsync;
+        }
+        if current.window.mode != started.window.mode {
+            changed |= self.window.mode != current.window.mode;
+            self.window.mode = current.window.mode;
+        }
+        if current.window.monitor != started.window.monitor {
+            changed |= self.window.monitor != current.window.monitor;
+            self.window.monitor = current.window.monitor;
+        }
+
+        changed
+    }
+
+    /// Set a single `section.field` key from its string form, lists are comma separated.
+    /// The config is left untouched if the result doesn't validate.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let invalid = || ConfigError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        let value = value.trim();
+
+        let mut config = self.clone();
+        match key {
+            "window.title" => config.window.title = value.to_string(),
+            "window.width" => config.window.width = value.parse().map_err(|_| invalid())?,
+            "window.height" => config.window.height = value.parse().map_err(|_| invalid())?,
+            "window.mode" => config.window.mode = parse_enum(value).ok_or_else(invalid)?,
+            "window.monitor" => config.window.monitor = value.parse().map_err(|_| invalid())?,
+            "window.headless" => config.window.headless = parse_bool(value).ok_or_else(invalid)?,
+            "renderer.vsync" => config.renderer.vsync = parse_bool(value).ok_or_else(invalid)?,
+            "renderer.msaa" => config.renderer.msaa = value.parse().map_err(|_| invalid())?,
+            "renderer.backends" => {
+                config.renderer.backends = parse_list(value).ok_or_else(invalid)?
+            }
+            "renderer.debug" => config.renderer.debug = parse_list(value).ok_or_else(invalid)?,
+            "renderer.update_rate" => {
+                config.renderer.update_rate = value.parse().map_err(|_| invalid())?
+            }
+            _ => return Err(ConfigError::UnknownKey(key.to_string())),
+        }
+
+        config.validate()?;
+        *self = config;
+        Ok(())
+    }
+
+    /// Apply a `section.field=value` override
+    pub fn apply_override(&mut self, assignment: &str) -> Result<(), ConfigError> {
+        match assignment.split_once('=') {
+            Some((key, value)) => self.set(key.trim(), value),
+            None => Err(ConfigError::InvalidOverride(assignment.to_string())),
+        }
+    }
+
+    /// Apply `GLFW_TEST_<FIELD>` environment variables, such as `GLFW_TEST_WIDTH=800`
+    pub fn apply_env(&mut self) -> Result<(), ConfigError> {
+        for key in KEYS {
+            let field = key.rsplit('.').next().unwrap_or(key);
+            let name = format!("{}{}", ENV_PREFIX, field.to_ascii_uppercase());
+            match std::env::var(&name) {
+                // Read the same way as `HEADLESS_ENV` so both code paths agree
+                Ok(value) if *key == "window.headless" => {
+                    self.window.headless = application::parse_env_flag(&value)
+                }
+                Ok(value) => self.set(key, &value)?,
+                Err(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |key: &'static str, reason: &str| {
+            Err(ConfigError::Invalid {
+                key,
+                reason: reason.to_string(),
+            })
+        };
+
+        // View rects are 16-bit, so anything larger can't be rendered to
+        let max = u16::MAX as u32;
+        if self.window.width == 0 || self.window.width > max {
+            return invalid("window.width", "must be between 1 and 65535");
+        }
+        if self.window.height == 0 || self.window.height > max {
+            return invalid("window.height", "must be between 1 and 65535");
+        }
+        if !matches!(self.renderer.msaa, 0 | 1 | 2 | 4 | 8 | 16) {
+            return invalid("renderer.msaa", "must be one of 0, 2, 4, 8 or 16");
+        }
+        if self.renderer.backends.is_empty() {
+            return invalid("renderer.backends", "at least one backend is required");
+        }
+        if self.renderer.update_rate == 0 {
+            return invalid("renderer.update_rate", "must be greater than 0");
+        }
+
+        Ok(())
+    }
+
+    pub fn reset_flags(&self) -> ResetFlags {
+        let mut flags = match self.renderer.msaa {
+            2 => ResetFlags::MSAA_X2,
+            4 => ResetFlags::MSAA_X4,
+            8 => ResetFlags::MSAA_X8,
+            16 => ResetFlags::MSAA_X16,
+            _ => ResetFlags::empty(),
+        };
+        if self.renderer.vsync {
+            flags |= ResetFlags::VSYNC;
+        }
+
+        flags
+    }
+
+    pub fn debug_flags(&self) -> DebugFlags {
+        self.renderer
+            .debug
+            .iter()
+            .fold(DebugFlags::empty(), |flags, option| flags | option.flags())
+    }
+
+    pub fn window_metadata(&self) -> WindowMetadata<'_> {
+        WindowMetadata::new(
+            &self.window.title,
+            self.window.width,
+            self.window.height,
+            WindowMode::Windowed,
+            self.debug_flags(),
+        )
+        .with_display_mode(self.window.mode)
+        .with_monitor(self.window.monitor)
+        .with_headless(self.window.headless)
+        .with_renderers(
+            self.renderer
+                .backends
+                .iter()
+                .map(|backend| backend.renderer_type()),
+        )
+        .with_reset_flags(self.reset_flags())
+        .with_update_rate(self.renderer.update_rate)
+    }
+}
+
+pub(crate) fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse a single lowercase enum name
+pub(crate) fn parse_enum<T: for<'de> Deserialize<'de>>(value: &str) -> Option<T> {
+    let deserializer: StrDeserializer<ValueError> = value.trim().into_deserializer();
+    T::deserialize(deserializer).ok()
+}
+
+/// Parse a comma separated list of lowercase enum names
+pub(crate) fn parse_list<T: for<'de> Deserialize<'de>>(value: &str) -> Option<Vec<T>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(parse_enum)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A path in the temp directory unique to this test run
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("glfw-test-{}-{}.toml", name, std::process::id()))
+    }
+
+    #[test]
+    fn saved_configs_load_back_unchanged() {
+        let path = temp_path("round-trip");
+        let mut config = Config::default();
+        config.set("window.title", "Round trip").unwrap();
+        config.set("window.mode", "borderless").unwrap();
+        config.set("renderer.debug", "stats,wireframe").unwrap();
+        config.save(&path).unwrap();
+
+        let loaded = Config::load(&path);
+        std::fs::remove_file(&path).ok();
+        assert_eq!(loaded.unwrap(), config);
+    }
+
+    #[test]
+    fn runtime_changes_are_persisted_without_overrides() {
+        let path = temp_path("runtime");
+        let mut file = Config::default();
+        file.set("window.width", "800").unwrap();
+        file.save(&path).unwrap();
+
+        let mut saved = Config::load(&path).unwrap();
+        // Started with a command line override, then vsync was turned off in the console
+        let mut started = saved.clone();
+        started.set("window.width", "1024").unwrap();
+        let mut current = started.clone();
+        current.set("renderer.vsync", "off").unwrap();
+        current.set("window.mode", "fullscreen").unwrap();
+
+        assert!(!saved.merge_changes(&started, &started));
+        assert!(saved.merge_changes(&started, &current));
+        saved.save(&path).unwrap();
+        let loaded = Config::load(&path);
+        std::fs::remove_file(&path).ok();
+
+        let loaded = loaded.unwrap();
+        assert!(!loaded.renderer.vsync);
+        assert_eq!(loaded.window.mode, DisplayMode::Fullscreen);
+        assert_eq!(loaded.window.width, 800);
+    }
+}
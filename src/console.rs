@@ -0,0 +1,824 @@
+use crate::application::Application;
+use crate::config;
+use crate::debug_text::{Attr, Color};
+use crate::error::ConsoleError;
+use crate::event::Event;
+use crate::platform::Resolution;
+use crate::renderer::Renderer;
+use glfw::{Action, Key, Modifiers};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+const SCROLLBACK_LINES: usize = 512;
+const HISTORY_LINES: usize = 100;
+/// Lines moved by page up and page down
+const PAGE_LINES: usize = 10;
+/// Size of a bgfx debug text cell in pixels
+const CELL_WIDTH: u32 = 8;
+const CELL_HEIGHT: u32 = 16;
+
+const OUTPUT_ATTR: Attr = Attr::new(Color::White, Color::Blue);
+const INPUT_ATTR: Attr = Attr::new(Color::LightGray, Color::Blue);
+const ERROR_ATTR: Attr = Attr::new(Color::LightRed, Color::Blue);
+const PROMPT_ATTR: Attr = Attr::new(Color::Yellow, Color::Black);
+const CURSOR_ATTR: Attr = Attr::new(Color::Black, Color::Yellow);
+
+/// Runs a console command with the arguments following its name
+pub type CommandFn = Box<dyn FnMut(&mut Application, &[&str]) -> Result<(), ConsoleError>>;
+/// Called after a cvar has been set from the console
+pub type CvarHook = Box<dyn FnMut(&mut Application, &CvarValue)>;
+
+/// Value of a console variable, new values are parsed as the kind it was registered with
+#[derive(Clone, Debug, PartialEq)]
+pub enum CvarValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl CvarValue {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CvarValue::Bool(_) => "0 or 1",
+            CvarValue::Int(_) => "an integer",
+            CvarValue::Float(_) => "a number",
+            CvarValue::Text(_) => "text",
+        }
+    }
+
+    /// Parse `value` as the same kind as `self`
+    pub fn parse_as(&self, value: &str) -> Option<Self> {
+        match self {
+            CvarValue::Bool(_) => config::parse_bool(value).map(CvarValue::Bool),
+            CvarValue::Int(_) => value.parse().ok().map(CvarValue::Int),
+            CvarValue::Float(_) => value.parse().ok().map(CvarValue::Float),
+            CvarValue::Text(_) => Some(CvarValue::Text(value.to_string())),
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            CvarValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            CvarValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            CvarValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            CvarValue::Text(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CvarValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CvarValue::Bool(value) => write!(f, "{}", *value as u8),
+            CvarValue::Int(value) => write!(f, "{}", value),
+            CvarValue::Float(value) => write!(f, "{}", value),
+            CvarValue::Text(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+struct Command {
+    help: String,
+    /// `None` while the command is running
+    run: Option<CommandFn>,
+}
+
+struct Cvar {
+    help: String,
+    value: CvarValue,
+    /// `None` while the hook is running
+    on_change: Option<CvarHook>,
+}
+
+enum Entry {
+    Command(Command),
+    Cvar(Cvar),
+}
+
+impl Entry {
+    fn help(&self) -> &str {
+        match self {
+            Entry::Command(command) => &command.help,
+            Entry::Cvar(cvar) => &cvar.help,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LineKind {
+    Output,
+    Input,
+    Error,
+}
+
+/// Drop-down console drawn with debug text, toggled with the backtick key
+pub struct Console {
+    open: bool,
+    /// Commands and cvars share one namespace, sorted for help and completion
+    entries: BTreeMap<String, Entry>,
+    input: String,
+    /// Byte offset into `input`, always on a char boundary
+    cursor: usize,
+    scrollback: VecDeque<(LineKind, String)>,
+    /// Lines scrolled up from the bottom of the scrollback
+    scroll: usize,
+    history: Vec<String>,
+    history_index: Option<usize>,
+    /// Input being typed before browsing the history, restored when leaving it
+    draft: String,
+    /// The toggle key's own text event is still to come and must not reach anything
+    skip_char: bool,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Console {
+    /// An empty console with the built-in `help`, `clear`, `echo` and `quit` commands
+    pub fn new() -> Self {
+        let mut console = Self {
+            open: false,
+            entries: BTreeMap::new(),
+            input: String::new(),
+            cursor: 0,
+            scrollback: VecDeque::new(),
+            scroll: 0,
+            history: Vec::new(),
+            history_index: None,
+            draft: String::new(),
+            skip_char: false,
+        };
+
+        console.register_command("help", "list commands and cvars", |app, _| {
+            let lines: Vec<String> = app
+                .console
+                .entries
+                .iter()
+                .map(|(name, entry)| format!("{:<16}{}", name, entry.help()))
+                .collect();
+            for line in lines {
+                app.console.print(&line);
+            }
+            Ok(())
+        });
+        console.register_command("clear", "clear the scrollback", |app, _| {
+            app.console.clear();
+            Ok(())
+        });
+        console.register_command("echo", "print the arguments", |app, args| {
+            app.console.print(&args.join(" "));
+            Ok(())
+        });
+        console.register_command("quit", "close the application", |app, _| {
+            app.set_should_close(true);
+            Ok(())
+        });
+
+        console
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Register `run` under `name`, replacing any command or cvar with that name
+    pub fn register_command(
+        &mut self,
+        name: &str,
+        help: &str,
+        run: impl FnMut(&mut Application, &[&str]) -> Result<(), ConsoleError> + 'static,
+    ) {
+        let command = Command {
+            help: help.to_string(),
+            run: Some(Box::new(run)),
+        };
+        self.entries
+            .insert(name.to_string(), Entry::Command(command));
+    }
+
+    /// Register a variable typed as `value`, `on_change` runs whenever it is set from the
+    /// console
+    pub fn register_cvar(
+        &mut self,
+        name: &str,
+        help: &str,
+        value: CvarValue,
+        on_change: impl FnMut(&mut Application, &CvarValue) + 'static,
+    ) {
+        let cvar = Cvar {
+            help: help.to_string(),
+            value,
+            on_change: Some(Box::new(on_change)),
+        };
+        self.entries.insert(name.to_string(), Entry::Cvar(cvar));
+    }
+
+    /// Remove a command or cvar, returning whether it existed
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.entries.remove(name).is_some()
+    }
+
+    pub fn cvar(&self, name: &str) -> Option<&CvarValue> {
+        match self.entries.get(name) {
+            Some(Entry::Cvar(cvar)) => Some(&cvar.value),
+            _ => None,
+        }
+    }
+
+    /// Change a cvar without running its hook, for values changed outside the console.
+    /// Returns `false` if there is no such cvar or `value` is of a different kind.
+    pub fn set_cvar(&mut self, name: &str, value: CvarValue) -> bool {
+        match self.entries.get_mut(name) {
+            Some(Entry::Cvar(cvar))
+                if std::mem::discriminant(&cvar.value) == std::mem::discriminant(&value) =>
+            {
+                cvar.value = value;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn print(&mut self, text: &str) {
+        self.push_lines(LineKind::Output, text);
+    }
+
+    pub fn print_error(&mut self, text: &str) {
+        self.push_lines(LineKind::Error, text);
+    }
+
+    /// Clear the scrollback, the history is kept
+    pub fn clear(&mut self) {
+        self.scrollback.clear();
+        self.scroll = 0;
+    }
+
+    fn push_lines(&mut self, kind: LineKind, text: &str) {
+        for line in text.lines() {
+            // Control characters would be read as escape sequences by bgfx
+            let line = line
+                .chars()
+                .map(|c| if c.is_control() { ' ' } else { c })
+                .collect();
+            self.scrollback.push_back((kind, line));
+            if self.scrollback.len() > SCROLLBACK_LINES {
+                self.scrollback.pop_front();
+            }
+        }
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Replace the input line, moving the cursor to its end
+    pub fn set_input(&mut self, input: &str) {
+        self.input = input.to_string();
+        self.cursor = self.input.len();
+    }
+
+    pub fn insert(&mut self, character: char) {
+        if character.is_control() {
+            return;
+        }
+        self.input.insert(self.cursor, character);
+        self.cursor += character.len_utf8();
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(previous) = self.previous_boundary() {
+            self.input.replace_range(previous..self.cursor, "");
+            self.cursor = previous;
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if let Some(next) = self.next_boundary() {
+            self.input.replace_range(self.cursor..next, "");
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.previous_boundary().unwrap_or(self.cursor);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = self.next_boundary().unwrap_or(self.cursor);
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.input.len();
+    }
+
+    fn previous_boundary(&self) -> Option<usize> {
+        self.input[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map(|(index, _)| index)
+    }
+
+    fn next_boundary(&self) -> Option<usize> {
+        self.input[self.cursor..]
+            .chars()
+            .next()
+            .map(|c| self.cursor + c.len_utf8())
+    }
+
+    /// Replace the input with the previous history entry
+    pub fn history_previous(&mut self) {
+        let index = match self.history_index {
+            Some(index) if index > 0 => index - 1,
+            None if !self.history.is_empty() => {
+                self.draft = std::mem::take(&mut self.input);
+                self.history.len() - 1
+            }
+            _ => return,
+        };
+
+        self.history_index = Some(index);
+        let entry = self.history[index].clone();
+        self.set_input(&entry);
+    }
+
+    /// Replace the input with the next history entry, or the draft after the newest one
+    pub fn history_next(&mut self) {
+        match self.history_index {
+            Some(index) if index + 1 < self.history.len() => {
+                self.history_index = Some(index + 1);
+                let entry = self.history[index + 1].clone();
+                self.set_input(&entry);
+            }
+            Some(_) => {
+                self.history_index = None;
+                let draft = std::mem::take(&mut self.draft);
+                self.set_input(&draft);
+            }
+            None => {}
+        }
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = (self.scroll + lines).min(self.scrollback.len());
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    /// Names of commands and cvars starting with `prefix`, sorted
+    pub fn completions(&self, prefix: &str) -> Vec<&str> {
+        self.entries
+            .range(prefix.to_string()..)
+            .map(|(name, _)| name.as_str())
+            .take_while(|name| name.starts_with(prefix))
+            .collect()
+    }
+
+    /// Complete the command name before the cursor. A single match is completed with a
+    /// trailing space, several are extended to their common prefix and listed.
+    pub fn complete(&mut self) {
+        let prefix = &self.input[..self.cursor];
+        if prefix.contains(char::is_whitespace) {
+            return;
+        }
+
+        let matches: Vec<String> = self
+            .completions(prefix)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let rest = self.input[self.cursor..].trim_start().to_string();
+        let completed = match matches.as_slice() {
+            [] => return,
+            [name] => format!("{} ", name),
+            [first, others @ ..] => {
+                let common = others
+                    .iter()
+                    .fold(first.as_str(), |common, name| common_prefix(common, name))
+                    .to_string();
+                self.print(&format!("  {}", matches.join("  ")));
+                common
+            }
+        };
+
+        self.input = format!("{}{}", completed, rest);
+        self.cursor = completed.len();
+    }
+
+    /// Take the input line, echoing it to the scrollback and recording it in the history.
+    /// Returns `None` for blank lines.
+    pub fn submit(&mut self) -> Option<String> {
+        let line = std::mem::take(&mut self.input);
+        self.cursor = 0;
+        self.scroll = 0;
+        self.history_index = None;
+        self.draft.clear();
+
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+
+        self.push_lines(LineKind::Input, &format!("> {}", line));
+        if self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+            if self.history.len() > HISTORY_LINES {
+                self.history.remove(0);
+            }
+        }
+
+        Some(line.to_string())
+    }
+
+    /// Run a command line, `name` alone prints a cvar and `name value` sets it
+    pub fn execute(app: &mut Application, line: &str) -> Result<(), ConsoleError> {
+        let tokens = tokenize(line)?;
+        let (name, args) = match tokens.split_first() {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        let cvar = match app.console.entries.get_mut(name.as_str()) {
+            None => return Err(ConsoleError::UnknownCommand(name.clone())),
+            Some(Entry::Command(command)) => {
+                let mut run = command
+                    .run
+                    .take()
+                    .ok_or_else(|| ConsoleError::Recursive(name.clone()))?;
+                // Taken out while it runs so it can reach the console through `app`
+                let result = run(app, &args);
+                if let Some(Entry::Command(command)) = app.console.entries.get_mut(name.as_str()) {
+                    command.run.get_or_insert(run);
+                }
+                return result;
+            }
+            Some(Entry::Cvar(cvar)) => cvar,
+        };
+
+        let value = match args.as_slice() {
+            [] => {
+                let line = format!("{} = {}, {}", name, cvar.value, cvar.help);
+                app.console.print(&line);
+                return Ok(());
+            }
+            [value] => cvar
+                .value
+                .parse_as(value)
+                .ok_or_else(|| ConsoleError::InvalidValue {
+                    name: name.clone(),
+                    value: value.to_string(),
+                    expected: cvar.value.kind(),
+                })?,
+            _ => return Err(ConsoleError::Usage(format!("{} <value>", name))),
+        };
+        cvar.value = value.clone();
+
+        if let Some(mut on_change) = cvar.on_change.take() {
+            on_change(app, &value);
+            if let Some(Entry::Cvar(cvar)) = app.console.entries.get_mut(name.as_str()) {
+                cvar.on_change.get_or_insert(on_change);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle the toggle key and, while open, every key press and text event. Key
+    /// releases aren't consumed so keys held while opening the console don't get stuck.
+    pub fn handle_event(app: &mut Application, event: &Event) -> bool {
+        match event {
+            Event::Key {
+                key: Key::GraveAccent,
+                action: Action::Press | Action::Repeat,
+                ..
+            } => {
+                let toggled = app.console.toggle_key(event.is_key_press(Key::GraveAccent));
+                // Debug text persists until cleared, remove the console from the screen
+                if toggled && !app.console.is_open() {
+                    if let Some(renderer) = app.renderer() {
+                        renderer.dbg_text_clear();
+                    }
+                }
+                return true;
+            }
+            Event::Char(_) if app.console.take_skip_char() => return true,
+            Event::Key {
+                action: Action::Press | Action::Repeat,
+                ..
+            } => app.console.skip_char = false,
+            _ => {}
+        }
+        if !app.console.is_open() {
+            return false;
+        }
+
+        let console = &mut app.console;
+        match event {
+            Event::Char(character) => console.insert(*character),
+            Event::Key {
+                key,
+                action: Action::Press | Action::Repeat,
+                modifiers,
+                ..
+            } => match key {
+                Key::Enter | Key::KpEnter => {
+                    if let Some(line) = console.submit() {
+                        if let Err(error) = Console::execute(app, &line) {
+                            app.console.print_error(&error.to_string());
+                        }
+                    }
+                }
+                Key::Escape => {
+                    console.set_open(false);
+                    if let Some(renderer) = app.renderer() {
+                        renderer.dbg_text_clear();
+                    }
+                }
+                Key::Backspace => console.backspace(),
+                Key::Delete => console.delete(),
+                Key::Left => console.move_left(),
+                Key::Right => console.move_right(),
+                Key::Home => console.move_home(),
+                Key::End => console.move_end(),
+                Key::Up => console.history_previous(),
+                Key::Down => console.history_next(),
+                Key::Tab => console.complete(),
+                Key::PageUp => console.scroll_up(PAGE_LINES),
+                Key::PageDown => console.scroll_down(PAGE_LINES),
+                Key::L if modifiers.contains(Modifiers::Control) => console.clear(),
+                _ => {}
+            },
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// The toggle key went down, `press` is `false` for key repeats. Whatever character the
+    /// layout maps the key to is swallowed, in both directions. Returns whether the
+    /// console was toggled.
+    fn toggle_key(&mut self, press: bool) -> bool {
+        self.skip_char = true;
+        if press {
+            self.toggle();
+        }
+        press
+    }
+
+    /// `true` once for the text event following the toggle key
+    fn take_skip_char(&mut self) -> bool {
+        std::mem::take(&mut self.skip_char)
+    }
+
+    /// Draw the console over the top half of the screen, a no-op while closed
+    pub fn render(&self, renderer: &Renderer, size: Resolution) {
+        if !self.open {
+            return;
+        }
+
+        let columns = (size.width / CELL_WIDTH).max(1) as usize;
+        let rows = (size.height / CELL_HEIGHT / 2).max(2) as usize;
+        let output_rows = rows - 1;
+
+        let end = self.scrollback.len().saturating_sub(self.scroll);
+        let start = end.saturating_sub(output_rows);
+        // Fewer lines than rows leaves blank rows at the top
+        let blank = output_rows - (end - start);
+        for row in 0..blank {
+            renderer.dbg_text(0, row as u16, OUTPUT_ATTR.0, &fit("", columns));
+        }
+        for (row, (kind, line)) in self.scrollback.range(start..end).enumerate() {
+            let attr = match kind {
+                LineKind::Output => OUTPUT_ATTR,
+                LineKind::Input => INPUT_ATTR,
+                LineKind::Error => ERROR_ATTR,
+            };
+            renderer.dbg_text(0, (blank + row) as u16, attr.0, &fit(line, columns));
+        }
+
+        // Scroll the input horizontally so the cursor stays visible
+        let prompt = format!("> {}", self.input);
+        let cursor = 2 + self.input[..self.cursor].chars().count();
+        let offset = (cursor + 1).saturating_sub(columns);
+        let visible: String = prompt.chars().skip(offset).collect();
+        let y = output_rows as u16;
+        renderer.dbg_text(0, y, PROMPT_ATTR.0, &fit(&visible, columns));
+
+        let under_cursor = self.input[self.cursor..].chars().next().unwrap_or(' ');
+        renderer.dbg_text(
+            (cursor - offset) as u16,
+            y,
+            CURSOR_ATTR.0,
+            &under_cursor.to_string(),
+        );
+    }
+}
+
+/// Truncate or pad `text` to exactly `columns` cells so the background covers the row
+fn fit(text: &str, columns: usize) -> String {
+    let mut fitted: String = text.chars().take(columns).collect();
+    let len = fitted.chars().count();
+    fitted.extend(std::iter::repeat(' ').take(columns - len));
+    fitted
+}
+
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let len = a
+        .char_indices()
+        .zip(b.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or_else(|| a.len().min(b.len()), |((index, _), _)| index);
+    &a[..len]
+}
+
+/// Split a command line on whitespace, double quotes group words into one argument
+fn tokenize(line: &str) -> Result<Vec<String>, ConsoleError> {
+    let mut tokens = Vec::new();
+    let mut token: Option<String> = None;
+    let mut quoted = false;
+
+    for character in line.chars() {
+        match character {
+            '"' => {
+                quoted = !quoted;
+                token.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => tokens.extend(token.take()),
+            c => token.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if quoted {
+        return Err(ConsoleError::UnterminatedQuote);
+    }
+    tokens.extend(token);
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_groups_quoted_words() {
+        assert_eq!(tokenize("  echo a   b ").unwrap(), ["echo", "a", "b"]);
+        assert_eq!(
+            tokenize(r#"bind jump "key:Space" x"y z""#).unwrap(),
+            ["bind", "jump", "key:Space", "xy z"]
+        );
+        // Empty quotes still make an argument
+        assert_eq!(tokenize(r#"set name """#).unwrap(), ["set", "name", ""]);
+        assert!(tokenize("").unwrap().is_empty());
+        assert!(matches!(
+            tokenize(r#"echo "open"#),
+            Err(ConsoleError::UnterminatedQuote)
+        ));
+    }
+
+    #[test]
+    fn complete_single_and_shared_prefixes() {
+        let mut console = Console::new();
+        console.register_cvar("vsync", "", CvarValue::Bool(true), |_, _| {});
+        console.register_cvar("volume", "", CvarValue::Float(1.0), |_, _| {});
+
+        console.set_input("he");
+        console.complete();
+        assert_eq!(console.input(), "help ");
+        assert_eq!(console.cursor, 5);
+
+        console.set_input("v");
+        console.complete();
+        assert_eq!(console.input(), "v");
+        console.set_input("vo");
+        console.complete();
+        assert_eq!(console.input(), "volume ");
+
+        // Several matches extend to their common prefix and are listed
+        console.register_command("quiet", "", |_, _| Ok(()));
+        console.set_input("qu");
+        console.complete();
+        assert_eq!(console.input(), "qui");
+        assert_eq!(console.scrollback.back().unwrap().1, "  quiet  quit");
+
+        // Arguments are never completed
+        console.set_input("echo he");
+        console.complete();
+        assert_eq!(console.input(), "echo he");
+    }
+
+    #[test]
+    fn history_browsing_restores_the_draft() {
+        let mut console = Console::new();
+        for line in ["first", "second", "second", "  "] {
+            console.set_input(line);
+            console.submit();
+        }
+        assert_eq!(console.history(), ["first", "second"]);
+
+        console.set_input("dra");
+        console.history_previous();
+        assert_eq!(console.input(), "second");
+        console.history_previous();
+        assert_eq!(console.input(), "first");
+        console.history_previous();
+        assert_eq!(console.input(), "first");
+
+        console.history_next();
+        assert_eq!(console.input(), "second");
+        console.history_next();
+        assert_eq!(console.input(), "dra");
+        console.history_next();
+        assert_eq!(console.input(), "dra");
+    }
+
+    #[test]
+    fn cvars_parse_as_their_registered_kind() {
+        let bool_value = CvarValue::Bool(false);
+        assert_eq!(bool_value.parse_as("1"), Some(CvarValue::Bool(true)));
+        assert_eq!(bool_value.parse_as("off"), Some(CvarValue::Bool(false)));
+        assert_eq!(bool_value.parse_as("maybe"), None);
+
+        assert_eq!(CvarValue::Int(0).parse_as("-3"), Some(CvarValue::Int(-3)));
+        assert_eq!(CvarValue::Int(0).parse_as("1.5"), None);
+        assert_eq!(
+            CvarValue::Float(0.0).parse_as("1.5"),
+            Some(CvarValue::Float(1.5))
+        );
+        assert_eq!(
+            CvarValue::Text(String::new()).parse_as("two words"),
+            Some(CvarValue::Text("two words".to_string()))
+        );
+        assert_eq!(CvarValue::Bool(true).to_string(), "1");
+        assert_eq!(CvarValue::Text("a".to_string()).to_string(), "\"a\"");
+    }
+
+    #[test]
+    fn set_cvar_keeps_the_registered_kind() {
+        let mut console = Console::new();
+        console.register_cvar("vsync", "", CvarValue::Bool(true), |_, _| {});
+
+        assert!(console.set_cvar("vsync", CvarValue::Bool(false)));
+        assert_eq!(console.cvar("vsync"), Some(&CvarValue::Bool(false)));
+        assert!(!console.set_cvar("vsync", CvarValue::Int(1)));
+        assert!(!console.set_cvar("help", CvarValue::Bool(true)));
+        assert!(!console.set_cvar("missing", CvarValue::Bool(true)));
+    }
+
+    #[test]
+    fn toggle_key_swallows_one_char_each_way() {
+        let mut console = Console::new();
+        assert!(console.toggle_key(true));
+        assert!(console.is_open());
+        assert!(console.take_skip_char());
+        assert!(!console.take_skip_char());
+
+        // Held keys repeat their text without toggling again
+        assert!(!console.toggle_key(false));
+        assert!(console.is_open());
+        assert!(console.take_skip_char());
+
+        assert!(console.toggle_key(true));
+        assert!(!console.is_open());
+        assert!(console.take_skip_char());
+    }
+}
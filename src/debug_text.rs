@@ -0,0 +1,432 @@
+use crate::error::AnsiError;
+use crate::renderer::Renderer;
+use std::fmt::Write;
+
+/// The 16 colors of the VGA text mode palette bgfx's debug font uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Color {
+    Black = 0,
+    Blue,
+    Green,
+    Cyan,
+    Red,
+    Magenta,
+    Brown,
+    LightGray,
+    DarkGray,
+    LightBlue,
+    LightGreen,
+    LightCyan,
+    LightRed,
+    LightMagenta,
+    Yellow,
+    White,
+}
+
+impl Color {
+    pub const ALL: [Color; 16] = [
+        Color::Black,
+        Color::Blue,
+        Color::Green,
+        Color::Cyan,
+        Color::Red,
+        Color::Magenta,
+        Color::Brown,
+        Color::LightGray,
+        Color::DarkGray,
+        Color::LightBlue,
+        Color::LightGreen,
+        Color::LightCyan,
+        Color::LightRed,
+        Color::LightMagenta,
+        Color::Yellow,
+        Color::White,
+    ];
+
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+}
+
+/// A `dbg_text` attribute byte, background in the high nibble and foreground in the low
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Attr(pub u8);
+
+impl Attr {
+    /// White on black, bgfx's usual text color
+    pub const DEFAULT: Attr = Attr::new(Color::White, Color::Black);
+
+    pub const fn new(fore: Color, back: Color) -> Self {
+        Attr(((back as u8) << 4) | fore as u8)
+    }
+
+    pub fn fore(self) -> Color {
+        Color::ALL[(self.0 & 0x0f) as usize]
+    }
+
+    pub fn back(self) -> Color {
+        Color::ALL[(self.0 >> 4) as usize]
+    }
+
+    pub fn with_fore(self, fore: Color) -> Self {
+        Attr::new(fore, self.back())
+    }
+
+    pub fn with_back(self, back: Color) -> Self {
+        Attr::new(self.fore(), back)
+    }
+}
+
+impl Default for Attr {
+    fn default() -> Self {
+        Attr::DEFAULT
+    }
+}
+
+/// Builds strings with the escape sequences bgfx understands, `ESC[<fore>;<back>m` with
+/// either color optional and `ESC[0m` to return to the attribute passed to `dbg_text`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AnsiBuilder {
+    text: String,
+}
+
+impl AnsiBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `text` in the base attribute
+    pub fn text(mut self, text: &str) -> Self {
+        self.text.push_str(text);
+        self
+    }
+
+    pub fn fore(self, color: Color, text: &str) -> Self {
+        self.span(Some(color), None, text)
+    }
+
+    pub fn back(self, color: Color, text: &str) -> Self {
+        self.span(None, Some(color), text)
+    }
+
+    pub fn styled(self, fore: Color, back: Color, text: &str) -> Self {
+        self.span(Some(fore), Some(back), text)
+    }
+
+    /// Append `text` with the given colors, then reset to the base attribute
+    pub fn span(mut self, fore: Option<Color>, back: Option<Color>, text: &str) -> Self {
+        let color = |color: Option<Color>| color.map_or(String::new(), |c| c.index().to_string());
+        // Writing to a String can't fail
+        let _ = write!(
+            self.text,
+            "\x1b[{};{}m{}\x1b[0m",
+            color(fore),
+            color(back),
+            text
+        );
+        self
+    }
+
+    pub fn build(self) -> String {
+        self.text
+    }
+}
+
+/// A run of text parsed out of an ANSI string, `None` colors come from the base attribute
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span<'a> {
+    pub text: &'a str,
+    pub fore: Option<Color>,
+    pub back: Option<Color>,
+}
+
+/// Split `text` into spans, rejecting escape sequences bgfx would misinterpret
+pub fn parse_ansi(text: &str) -> Result<Vec<Span<'_>>, AnsiError> {
+    let mut spans = Vec::new();
+    let (mut fore, mut back) = (None, None);
+    let mut start = 0;
+    let mut rest = text;
+
+    while let Some(escape) = rest.find('\x1b') {
+        let offset = text.len() - rest.len() + escape;
+        if escape > 0 {
+            spans.push(Span {
+                text: &rest[..escape],
+                fore,
+                back,
+            });
+        }
+
+        let sequence = &rest[escape + 1..];
+        let body = sequence
+            .strip_prefix('[')
+            .ok_or(AnsiError::Unsupported { offset })?;
+        let end = body.find('m').ok_or(AnsiError::Unterminated { offset })?;
+        let body = &body[..end];
+
+        match body.split_once(';') {
+            // A lone `0` resets to the base attribute
+            None if body.trim() == "0" => (fore, back) = (None, None),
+            None => fore = parse_color(body, offset)?.or(fore),
+            Some((fore_part, back_part)) => {
+                fore = parse_color(fore_part, offset)?.or(fore);
+                back = parse_color(back_part, offset)?.or(back);
+            }
+        }
+
+        // Skip `ESC[`, the body and the closing `m`
+        rest = &sequence[1 + end + 1..];
+        start = text.len() - rest.len();
+    }
+
+    if start < text.len() {
+        spans.push(Span {
+            text: &text[start..],
+            fore,
+            back,
+        });
+    }
+
+    Ok(spans)
+}
+
+/// Parse one side of a color sequence, an empty side keeps the current color
+fn parse_color(value: &str, offset: usize) -> Result<Option<Color>, AnsiError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    value
+        .parse()
+        .ok()
+        .and_then(Color::from_index)
+        .map(Some)
+        .ok_or_else(|| AnsiError::InvalidColor {
+            offset,
+            value: value.to_string(),
+        })
+}
+
+/// Number of character cells `text` covers once escape sequences are stripped
+pub fn visible_width(text: &str) -> Result<usize, AnsiError> {
+    Ok(parse_ansi(text)?
+        .iter()
+        .map(|span| span.text.chars().count())
+        .sum())
+}
+
+/// Prints debug text at a cursor that advances like a terminal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DebugText {
+    /// Column new lines return to
+    pub left: u16,
+    pub x: u16,
+    pub y: u16,
+    pub attr: Attr,
+}
+
+impl DebugText {
+    pub fn new(x: u16, y: u16) -> Self {
+        Self {
+            left: x,
+            x,
+            y,
+            attr: Attr::DEFAULT,
+        }
+    }
+
+    pub fn with_attr(mut self, attr: Attr) -> Self {
+        self.attr = attr;
+        self
+    }
+
+    pub fn move_to(&mut self, x: u16, y: u16) {
+        self.left = x;
+        self.x = x;
+        self.y = y;
+    }
+
+    /// Print `text` at the cursor and advance past it. Invalid escape sequences are
+    /// reported before anything is printed.
+    pub fn print(&mut self, renderer: &Renderer, text: &str) -> Result<(), AnsiError> {
+        let (x, y) = (self.x, self.y);
+        self.advance(text)?;
+        renderer.dbg_text(x, y, self.attr.0, text);
+        Ok(())
+    }
+
+    /// Move the cursor past `text` without printing it
+    pub fn advance(&mut self, text: &str) -> Result<(), AnsiError> {
+        let width = visible_width(text)?;
+        self.x = self.x.saturating_add(width.min(u16::MAX as usize) as u16);
+        Ok(())
+    }
+
+    /// Print `text` and move to the start of the next line
+    pub fn println(&mut self, renderer: &Renderer, text: &str) -> Result<(), AnsiError> {
+        self.print(renderer, text)?;
+        self.newline();
+        Ok(())
+    }
+
+    pub fn newline(&mut self) {
+        self.x = self.left;
+        self.y = self.y.saturating_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attr_packs_background_in_the_high_nibble() {
+        assert_eq!(Attr::DEFAULT.0, 0x0f);
+        assert_eq!(Attr::new(Color::White, Color::Cyan).0, 0x3f);
+
+        let attr = Attr::new(Color::LightRed, Color::Blue);
+        assert_eq!(attr.fore(), Color::LightRed);
+        assert_eq!(attr.back(), Color::Blue);
+        assert_eq!(
+            attr.with_fore(Color::Yellow),
+            Attr::new(Color::Yellow, Color::Blue)
+        );
+        assert_eq!(
+            attr.with_back(Color::Black),
+            Attr::new(Color::LightRed, Color::Black)
+        );
+    }
+
+    #[test]
+    fn colors_round_trip_through_their_index() {
+        for (index, color) in Color::ALL.iter().enumerate() {
+            assert_eq!(color.index() as usize, index);
+            assert_eq!(Color::from_index(index as u8), Some(*color));
+        }
+        assert_eq!(Color::from_index(16), None);
+    }
+
+    #[test]
+    fn builder_emits_bgfx_sequences() {
+        assert_eq!(
+            AnsiBuilder::new().fore(Color::LightBlue, "e").build(),
+            "\x1b[9;me\x1b[0m"
+        );
+        assert_eq!(
+            AnsiBuilder::new().back(Color::Cyan, "  ").build(),
+            "\x1b[;3m  \x1b[0m"
+        );
+        assert_eq!(
+            AnsiBuilder::new()
+                .text("a ")
+                .styled(Color::White, Color::Red, "b")
+                .build(),
+            "a \x1b[15;4mb\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn builder_output_parses_back() {
+        let text = AnsiBuilder::new()
+            .text("plain ")
+            .fore(Color::Yellow, "warm")
+            .build();
+        let spans = parse_ansi(&text).unwrap();
+        assert_eq!(
+            spans,
+            [
+                Span {
+                    text: "plain ",
+                    fore: None,
+                    back: None,
+                },
+                Span {
+                    text: "warm",
+                    fore: Some(Color::Yellow),
+                    back: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_handles_partial_and_reset_sequences() {
+        let spans = parse_ansi("\x1b[;5ma\x1b[ 3mb\x1b[0mc").unwrap();
+        assert_eq!(
+            spans,
+            [
+                Span {
+                    text: "a",
+                    fore: None,
+                    back: Some(Color::Magenta),
+                },
+                Span {
+                    text: "b",
+                    fore: Some(Color::Cyan),
+                    back: Some(Color::Magenta),
+                },
+                Span {
+                    text: "c",
+                    fore: None,
+                    back: None,
+                },
+            ]
+        );
+
+        // The demo strings pad the background index with a space
+        let spans = parse_ansi("\x1b[; 2m  ").unwrap();
+        assert_eq!(spans[0].back, Some(Color::Green));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_sequences() {
+        assert!(matches!(
+            parse_ansi("ab\x1b(0"),
+            Err(AnsiError::Unsupported { offset: 2 })
+        ));
+        assert!(matches!(
+            parse_ansi("\x1b[9;"),
+            Err(AnsiError::Unterminated { offset: 0 })
+        ));
+        match parse_ansi("x\x1b[16;m") {
+            Err(AnsiError::InvalidColor { offset, value }) => {
+                assert_eq!(offset, 1);
+                assert_eq!(value, "16");
+            }
+            other => panic!("expected an invalid color, got {:?}", other),
+        }
+        assert!(matches!(
+            parse_ansi("\x1b[;bluem"),
+            Err(AnsiError::InvalidColor { .. })
+        ));
+    }
+
+    #[test]
+    fn visible_width_skips_escapes() {
+        assert_eq!(visible_width("").unwrap(), 0);
+        assert_eq!(visible_width("\x1b[9;mé\x1b[0m  ").unwrap(), 3);
+        assert!(visible_width("\x1b[").is_err());
+    }
+
+    #[test]
+    fn cursor_advances_and_wraps_to_its_left_edge() {
+        let mut text = DebugText::new(4, 2);
+        text.advance("\x1b[12;mError\x1b[0m: ").unwrap();
+        assert_eq!((text.x, text.y), (11, 2));
+
+        text.newline();
+        assert_eq!((text.x, text.y), (4, 3));
+
+        assert!(text.advance("\x1b[99m").is_err());
+        assert_eq!(text.x, 4);
+
+        text.move_to(0, 10);
+        assert_eq!((text.left, text.x, text.y), (0, 0, 10));
+    }
+}
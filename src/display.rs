@@ -0,0 +1,73 @@
+use glfw::{Monitor, VidMode};
+use serde::{Deserialize, Serialize};
+
+/// How the application window occupies its monitor
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayMode {
+    Windowed,
+    /// An undecorated window covering the whole monitor at its desktop resolution
+    Borderless,
+    /// Exclusive fullscreen, changing the monitor's video mode if needed
+    Fullscreen,
+}
+
+impl Default for DisplayMode {
+    fn default() -> Self {
+        DisplayMode::Windowed
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VideoMode {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_rate: u32,
+}
+
+impl From<VidMode> for VideoMode {
+    fn from(mode: VidMode) -> Self {
+        Self {
+            width: mode.width,
+            height: mode.height,
+            refresh_rate: mode.refresh_rate,
+        }
+    }
+}
+
+/// A snapshot of a connected monitor
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonitorInfo {
+    /// Position in the list returned by `Application::monitors`, `0` is the primary monitor
+    pub index: usize,
+    pub name: String,
+    /// Top-left corner on the virtual desktop, in screen coordinates
+    pub position: (i32, i32),
+    pub current_mode: Option<VideoMode>,
+    pub video_modes: Vec<VideoMode>,
+}
+
+impl MonitorInfo {
+    pub(crate) fn new(index: usize, monitor: &Monitor) -> Self {
+        Self {
+            index,
+            name: monitor.get_name().unwrap_or_default(),
+            position: monitor.get_pos(),
+            current_mode: monitor.get_video_mode().map(VideoMode::from),
+            video_modes: monitor
+                .get_video_modes()
+                .into_iter()
+                .map(VideoMode::from)
+                .collect(),
+        }
+    }
+}
+
+/// Window position and size to restore when leaving fullscreen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
@@ -1,19 +1,273 @@
+use crate::shader::ShaderStage;
+use bgfx_rs::static_lib::RendererType;
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Failed to initialize {0}")]
     Initialization(#[from] InitializationError),
+    #[error("Failed to load map: {0}")]
+    Map(#[from] MapError),
+    #[error("Failed to load input bindings: {0}")]
+    Input(#[from] InputError),
+    #[error("Invalid configuration: {0}")]
+    Config(#[from] ConfigError),
+    #[error("Invalid arguments: {0}")]
+    Cli(#[from] CliError),
+    #[error("Failed to load shader: {0}")]
+    Shader(#[from] ShaderError),
+    #[error("Failed to load texture: {0}")]
+    Texture(#[from] TextureError),
+    #[error("Failed to load font: {0}")]
+    Font(#[from] FontError),
+    #[error("Invalid debug text: {0}")]
+    Ansi(#[from] AnsiError),
+}
+
+impl Error {
+    /// Process exit code reported for this error
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Cli(_) => 2,
+            Error::Config(_) => 3,
+            Error::Initialization(_) => 4,
+            Error::Map(_) => 5,
+            Error::Input(_) => 6,
+            Error::Shader(_) => 7,
+            Error::Texture(_) => 8,
+            Error::Font(_) => 9,
+            Error::Ansi(_) => 10,
+        }
+    }
 }
 
 #[derive(Debug, Error)]
 pub enum InitializationError {
     #[error("glfw")]
-    Glfw,
-    #[error("window")]
-    Window,
-    #[error("bgfx")]
-    Bgfx,
+    Glfw(#[source] GlfwError),
+    #[error("a {width}x{height} window")]
+    Window {
+        width: u32,
+        height: u32,
+        #[source]
+        source: Option<GlfwError>,
+    },
+    #[error("bgfx with {renderers:?} at {width}x{height} on a {window} window")]
+    Bgfx {
+        renderers: Vec<RendererType>,
+        width: u32,
+        height: u32,
+        /// Kind of raw window handle passed to bgfx, such as `Xlib` or `Win32`
+        window: &'static str,
+    },
+    #[error("bgfx, it is already running")]
+    AlreadyInitialized,
+    #[error("bgfx on a {0} window, which has no platform data mapping")]
+    UnsupportedWindowHandle(&'static str),
+}
+
+/// An error reported through the glfw error callback
+#[derive(Clone, Debug, Error)]
+#[error("{description} ({code:?})")]
+pub struct GlfwError {
+    /// `None` when glfw failed without reporting through the callback
+    pub code: Option<glfw::Error>,
+    pub description: String,
+}
+
+#[derive(Debug, Error)]
+pub enum MapError {
+    #[error("could not read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("unrecognized map format {0}, expected .tmx, .tmj, .tsx or .tsj")]
+    Format(PathBuf),
+    #[error("invalid json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid xml: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("<{element}> is missing attribute `{attribute}`")]
+    MissingAttribute {
+        element: String,
+        attribute: &'static str,
+    },
+    #[error("invalid value `{value}` for `{field}`")]
+    InvalidValue { field: String, value: String },
+    #[error("invalid base64 tile data: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("could not decompress tile data: {0}")]
+    Decompress(#[source] std::io::Error),
+    #[error("layer `{layer}` is {width}x{height}, the map is {map_width}x{map_height}")]
+    LayerSize {
+        layer: String,
+        width: u32,
+        height: u32,
+        map_width: u32,
+        map_height: u32,
+    },
+    #[error("layer `{layer}` has {actual} tiles, expected {expected}")]
+    TileCount {
+        layer: String,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("unsupported {0}")]
+    Unsupported(String),
+}
+
+#[derive(Debug, Error)]
+pub enum InputError {
+    #[error("could not access {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("invalid bindings file: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("could not serialize bindings: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error("unknown binding `{0}`")]
+    UnknownBinding(String),
+    #[error("glfw rejected the gamepad mappings")]
+    GamepadMappings,
+    #[error("gamepad mappings were not applied, there is no glfw context when headless")]
+    HeadlessGamepadMappings,
+}
+
+#[derive(Debug, Error)]
+pub enum ShaderError {
+    #[error("no compiled shaders for the {0:?} backend")]
+    UnsupportedBackend(RendererType),
+    #[error("{0} not found, compile the shader sources for this backend")]
+    Missing(PathBuf),
+    #[error("could not read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{0} is not a bgfx shader binary")]
+    Invalid(PathBuf),
+    #[error("{path} is not a {expected:?} shader")]
+    StageMismatch {
+        path: PathBuf,
+        expected: ShaderStage,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum TextureError {
+    #[error("could not read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("could not decode {path}: {source}")]
+    Decode {
+        path: PathBuf,
+        #[source]
+        source: image::ImageError,
+    },
+    #[error("invalid texture container {path}: {reason}")]
+    Container { path: PathBuf, reason: &'static str },
+    #[error("unsupported texture format {0}, expected .png, .jpg, .tga, .dds or .ktx")]
+    Unsupported(PathBuf),
+    #[error("{width}x{height} is outside the supported texture size")]
+    InvalidSize { width: u32, height: u32 },
+    #[error("only textures created with Texture::dynamic can be updated")]
+    Immutable,
+    #[error("{width}x{height} update at ({x}, {y}) is outside the texture")]
+    UpdateOutOfBounds {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum FontError {
+    #[error("could not read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("could not parse {path}: {reason}")]
+    Parse { path: PathBuf, reason: &'static str },
+    #[error("could not upload the glyph atlas: {0}")]
+    Texture(#[from] TextureError),
+    #[error("font {0} was not loaded by this Fonts")]
+    UnknownFont(usize),
+}
+
+#[derive(Debug, Error)]
+pub enum AnsiError {
+    #[error("unsupported escape sequence at byte {offset}, expected `ESC[`")]
+    Unsupported { offset: usize },
+    #[error("escape sequence at byte {offset} is missing its closing `m`")]
+    Unterminated { offset: usize },
+    #[error("invalid color `{value}` at byte {offset}, expected 0 to 15")]
+    InvalidColor { offset: usize, value: String },
+}
+
+#[derive(Debug, Error)]
+pub enum ConsoleError {
+    #[error("unknown command `{0}`")]
+    UnknownCommand(String),
+    #[error("usage: {0}")]
+    Usage(String),
+    #[error("invalid value `{value}` for `{name}`, expected {expected}")]
+    InvalidValue {
+        name: String,
+        value: String,
+        expected: &'static str,
+    },
+    #[error("unterminated quote")]
+    UnterminatedQuote,
+    #[error("`{0}` is already running")]
+    Recursive(String),
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("could not access {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("could not parse config file: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("could not serialize config: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error("unknown key `{0}`")]
+    UnknownKey(String),
+    #[error("invalid value `{value}` for `{key}`")]
+    InvalidValue { key: String, value: String },
+    #[error("expected `key=value`, found `{0}`")]
+    InvalidOverride(String),
+    #[error("`{key}` {reason}")]
+    Invalid { key: &'static str, reason: String },
+}
+
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error("unknown argument `{0}`")]
+    UnknownArgument(String),
+    #[error("`{0}` requires a value")]
+    MissingValue(String),
+    #[error("invalid value `{value}` for `{flag}`")]
+    InvalidValue { flag: String, value: String },
+    /// The options parsed but left the config invalid, such as a bad `--set`
+    #[error("{0}")]
+    Config(#[from] ConfigError),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,309 @@
+use crate::application::Application;
+use glfw::{
+    Action, GamepadAxis, GamepadButton, JoystickId, Key, Modifiers, MouseButton, WindowEvent,
+};
+use std::path::PathBuf;
+
+/// Window and input events delivered to handlers and scenes
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    Key {
+        key: Key,
+        scancode: i32,
+        action: Action,
+        modifiers: Modifiers,
+    },
+    /// Text input, already translated through the keyboard layout
+    Char(char),
+    MouseButton {
+        button: MouseButton,
+        action: Action,
+        modifiers: Modifiers,
+    },
+    CursorMoved {
+        x: f64,
+        y: f64,
+    },
+    CursorEntered(bool),
+    Scroll {
+        x: f64,
+        y: f64,
+    },
+    /// The framebuffer changed size, in pixels
+    Resized {
+        width: u32,
+        height: u32,
+    },
+    WindowMoved {
+        x: i32,
+        y: i32,
+    },
+    Focused(bool),
+    Iconified(bool),
+    CloseRequested,
+    FileDrop(Vec<PathBuf>),
+    GamepadConnected {
+        id: JoystickId,
+        name: String,
+    },
+    GamepadDisconnected {
+        id: JoystickId,
+    },
+    GamepadButton {
+        id: JoystickId,
+        button: GamepadButton,
+        action: Action,
+    },
+    /// Sent when an axis changes, after the deadzone has been applied
+    GamepadAxis {
+        id: JoystickId,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+impl Event {
+    /// Convert a glfw event, `None` for events the crate doesn't expose
+    pub fn from_window_event(event: WindowEvent) -> Option<Self> {
+        let event = match event {
+            WindowEvent::Key(key, scancode, action, modifiers) => Event::Key {
+                key,
+                scancode,
+                action,
+                modifiers,
+            },
+            WindowEvent::Char(character) => Event::Char(character),
+            WindowEvent::MouseButton(button, action, modifiers) => Event::MouseButton {
+                button,
+                action,
+                modifiers,
+            },
+            WindowEvent::CursorPos(x, y) => Event::CursorMoved { x, y },
+            WindowEvent::CursorEnter(entered) => Event::CursorEntered(entered),
+            WindowEvent::Scroll(x, y) => Event::Scroll { x, y },
+            WindowEvent::FramebufferSize(width, height) => Event::Resized {
+                width: width.max(0) as u32,
+                height: height.max(0) as u32,
+            },
+            WindowEvent::Pos(x, y) => Event::WindowMoved { x, y },
+            WindowEvent::Focus(focused) => Event::Focused(focused),
+            WindowEvent::Iconify(iconified) => Event::Iconified(iconified),
+            WindowEvent::Close => Event::CloseRequested,
+            WindowEvent::FileDrop(paths) => Event::FileDrop(paths),
+            _ => return None,
+        };
+
+        Some(event)
+    }
+
+    /// Whether this is a press of `key`, ignoring repeats
+    pub fn is_key_press(&self, key: Key) -> bool {
+        matches!(self, Event::Key { key: pressed, action: Action::Press, .. } if *pressed == key)
+    }
+}
+
+/// Returned when registering a handler, used to remove it again
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HandlerId(u64);
+
+/// Returns `true` when the event was consumed and shouldn't be delivered any further
+pub type EventHandler<C = Application> = Box<dyn FnMut(&mut C, &Event) -> bool>;
+
+/// Delivers events to handlers in the order they were registered, each handler receives
+/// the context `C` alongside the event
+pub struct EventDispatcher<C = Application> {
+    handlers: Vec<(HandlerId, EventHandler<C>)>,
+    next_id: u64,
+    /// Set while the handlers are taken out for dispatch
+    detached: bool,
+    /// Removals requested while detached, applied on `restore`
+    removed: Vec<HandlerId>,
+}
+
+impl<C> Default for EventDispatcher<C> {
+    fn default() -> Self {
+        Self {
+            handlers: Vec::new(),
+            next_id: 0,
+            detached: false,
+            removed: Vec::new(),
+        }
+    }
+}
+
+impl<C> EventDispatcher<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_handler(
+        &mut self,
+        handler: impl FnMut(&mut C, &Event) -> bool + 'static,
+    ) -> HandlerId {
+        let id = HandlerId(self.next_id);
+        self.next_id += 1;
+        self.handlers.push((id, Box::new(handler)));
+        id
+    }
+
+    pub fn remove_handler(&mut self, id: HandlerId) -> bool {
+        let len = self.handlers.len();
+        self.handlers.retain(|(handler, _)| *handler != id);
+        if self.handlers.len() != len {
+            return true;
+        }
+
+        if self.detached {
+            self.removed.push(id);
+        }
+        self.detached
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+
+    /// Offer `event` to each handler until one consumes it
+    pub fn dispatch(&mut self, context: &mut C, event: &Event) -> bool {
+        self.handlers
+            .iter_mut()
+            .any(|(_, handler)| handler(context, event))
+    }
+
+    /// Take the handlers out for dispatch, leaving an empty dispatcher that keeps
+    /// handing out unique ids
+    pub(crate) fn take(&mut self) -> EventDispatcher<C> {
+        let empty = EventDispatcher {
+            next_id: self.next_id,
+            detached: true,
+            ..Default::default()
+        };
+        std::mem::replace(self, empty)
+    }
+
+    /// Merge changes made to the dispatcher left behind by `take`
+    pub(crate) fn restore(&mut self, taken: EventDispatcher<C>) {
+        let changes = std::mem::replace(self, taken);
+        self.next_id = self.next_id.max(changes.next_id);
+        self.handlers.extend(changes.handlers);
+        self.handlers
+            .retain(|(id, _)| !changes.removed.contains(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: Key, action: Action, modifiers: Modifiers) -> Event {
+        Event::Key {
+            key,
+            scancode: 0,
+            action,
+            modifiers,
+        }
+    }
+
+    /// A handler that records `name` and consumes the event when `consume` is set
+    fn record(
+        name: &'static str,
+        consume: bool,
+    ) -> impl FnMut(&mut Vec<&'static str>, &Event) -> bool {
+        move |log, _| {
+            log.push(name);
+            consume
+        }
+    }
+
+    #[test]
+    fn handlers_run_in_registration_order() {
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.add_handler(record("first", false));
+        dispatcher.add_handler(record("second", false));
+        dispatcher.add_handler(record("third", false));
+
+        let mut log = Vec::new();
+        assert!(!dispatcher.dispatch(&mut log, &Event::CloseRequested));
+        assert_eq!(log, ["first", "second", "third"]);
+    }
+
+    #[test]
+    fn consumed_events_stop_propagating() {
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.add_handler(record("first", false));
+        let consumer = dispatcher.add_handler(record("consumer", true));
+        dispatcher.add_handler(record("last", false));
+
+        let mut log = Vec::new();
+        assert!(dispatcher.dispatch(&mut log, &Event::CloseRequested));
+        assert_eq!(log, ["first", "consumer"]);
+
+        assert!(dispatcher.remove_handler(consumer));
+        log.clear();
+        assert!(!dispatcher.dispatch(&mut log, &Event::CloseRequested));
+        assert_eq!(log, ["first", "last"]);
+    }
+
+    #[test]
+    fn changes_while_taken_apply_on_restore() {
+        let mut dispatcher = EventDispatcher::new();
+        let first = dispatcher.add_handler(record("first", false));
+
+        let taken = dispatcher.take();
+        assert!(dispatcher.remove_handler(first));
+        let added = dispatcher.add_handler(record("added", false));
+        assert_ne!(added, first);
+        dispatcher.restore(taken);
+
+        let mut log = Vec::new();
+        dispatcher.dispatch(&mut log, &Event::CloseRequested);
+        assert_eq!(log, ["added"]);
+    }
+
+    #[test]
+    fn converts_window_events() {
+        assert_eq!(
+            Event::from_window_event(WindowEvent::Key(
+                Key::Enter,
+                28,
+                Action::Press,
+                Modifiers::Alt
+            )),
+            Some(Event::Key {
+                key: Key::Enter,
+                scancode: 28,
+                action: Action::Press,
+                modifiers: Modifiers::Alt,
+            })
+        );
+        assert_eq!(
+            Event::from_window_event(WindowEvent::CursorPos(1.5, 2.0)),
+            Some(Event::CursorMoved { x: 1.5, y: 2.0 })
+        );
+        assert_eq!(
+            Event::from_window_event(WindowEvent::Close),
+            Some(Event::CloseRequested)
+        );
+        assert_eq!(Event::from_window_event(WindowEvent::Refresh), None);
+    }
+
+    #[test]
+    fn negative_framebuffer_sizes_clamp_to_zero() {
+        assert_eq!(
+            Event::from_window_event(WindowEvent::FramebufferSize(-1, 600)),
+            Some(Event::Resized {
+                width: 0,
+                height: 600,
+            })
+        );
+    }
+
+    #[test]
+    fn key_press_ignores_repeats_and_releases() {
+        assert!(key(Key::A, Action::Press, Modifiers::empty()).is_key_press(Key::A));
+        assert!(key(Key::A, Action::Press, Modifiers::Shift).is_key_press(Key::A));
+        assert!(!key(Key::A, Action::Repeat, Modifiers::empty()).is_key_press(Key::A));
+        assert!(!key(Key::A, Action::Release, Modifiers::empty()).is_key_press(Key::A));
+        assert!(!key(Key::B, Action::Press, Modifiers::empty()).is_key_press(Key::A));
+    }
+}
@@ -0,0 +1,492 @@
+use crate::error::FontError;
+use crate::renderer::Renderer;
+use crate::sprite::{Sprite, SpriteBatch};
+use crate::texture::{Texture, TextureOptions, Wrap};
+use fontdue::{Font, FontSettings};
+use glam::{Vec2, Vec4};
+use image::{Rgba, RgbaImage};
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+const INITIAL_ATLAS_SIZE: u32 = 512;
+const MAX_ATLAS_SIZE: u32 = 4096;
+/// Empty texels around each glyph so filtering doesn't bleed between neighbours
+const PADDING: u32 = 1;
+
+/// A font loaded by one `Fonts`, tagged with it so ids from another can be rejected
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FontId {
+    owner: u32,
+    index: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    /// Lines start at the anchor
+    Left,
+    /// Lines are centered on the anchor
+    Center,
+    /// Lines end at the anchor
+    Right,
+}
+
+/// How a string is laid out and drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextStyle {
+    /// Pixel size glyphs are rasterized at
+    pub size: f32,
+    /// Multiplier from rasterized pixels to the units of the view, such as world units
+    pub scale: f32,
+    pub color: Vec4,
+    pub align: Align,
+    /// Wrap lines at spaces once they would grow wider than this, in rasterized pixels
+    pub max_width: Option<f32>,
+    /// Multiplier on the font's own line spacing
+    pub line_height: f32,
+    pub layer: i32,
+    pub depth: f32,
+}
+
+impl TextStyle {
+    pub fn new(size: f32) -> Self {
+        Self {
+            size,
+            scale: 1.0,
+            color: Vec4::ONE,
+            align: Align::Left,
+            max_width: None,
+            line_height: 1.0,
+            layer: 0,
+            depth: 0.0,
+        }
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_color(mut self, color: Vec4) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    pub fn with_line_height(mut self, line_height: f32) -> Self {
+        self.line_height = line_height;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn with_depth(mut self, depth: f32) -> Self {
+        self.depth = depth;
+        self
+    }
+}
+
+/// A rasterized glyph, in atlas pixels
+#[derive(Clone, Copy, Debug)]
+struct Glyph {
+    position: (u32, u32),
+    size: (u32, u32),
+    /// Top-left corner relative to the pen position on the baseline, y down
+    offset: Vec2,
+}
+
+/// A glyph placed by `Fonts::layout`, relative to the anchor in rasterized pixels
+#[derive(Clone, Copy, Debug)]
+struct PlacedGlyph {
+    glyph: Glyph,
+    position: Vec2,
+}
+
+/// Shelf packed RGBA atlas with white texels and coverage in alpha, so glyphs are tinted
+/// by the sprite color
+struct GlyphAtlas {
+    image: RgbaImage,
+    cursor: (u32, u32),
+    shelf_height: u32,
+    /// `(min, max)` corners of the texels changed since the last upload
+    dirty: Option<((u32, u32), (u32, u32))>,
+}
+
+impl GlyphAtlas {
+    fn new() -> Self {
+        Self {
+            image: RgbaImage::from_pixel(
+                INITIAL_ATLAS_SIZE,
+                INITIAL_ATLAS_SIZE,
+                Rgba([255, 255, 255, 0]),
+            ),
+            cursor: (PADDING, PADDING),
+            shelf_height: 0,
+            dirty: None,
+        }
+    }
+
+    /// Reserve a `width` by `height` rectangle, growing the atlas when it's full
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        loop {
+            let (atlas_width, atlas_height) = self.image.dimensions();
+            if self.cursor.0 + width + PADDING > atlas_width {
+                self.cursor = (PADDING, self.cursor.1 + self.shelf_height + PADDING);
+                self.shelf_height = 0;
+            }
+            if width + PADDING * 2 <= atlas_width
+                && self.cursor.1 + height + PADDING <= atlas_height
+            {
+                let position = self.cursor;
+                self.cursor.0 += width + PADDING;
+                self.shelf_height = self.shelf_height.max(height);
+                return Some(position);
+            }
+
+            if atlas_width >= MAX_ATLAS_SIZE {
+                return None;
+            }
+            self.grow();
+        }
+    }
+
+    /// Double the atlas, existing glyphs keep their pixel positions
+    fn grow(&mut self) {
+        let (width, height) = self.image.dimensions();
+        let mut image = RgbaImage::from_pixel(width * 2, height * 2, Rgba([255, 255, 255, 0]));
+        image::imageops::replace(&mut image, &self.image, 0, 0);
+        self.image = image;
+    }
+
+    fn insert(&mut self, width: u32, height: u32, coverage: &[u8]) -> Option<(u32, u32)> {
+        let (x, y) = self.allocate(width, height)?;
+        for (index, alpha) in coverage.iter().enumerate() {
+            let (dx, dy) = (index as u32 % width, index as u32 / width);
+            self.image
+                .put_pixel(x + dx, y + dy, Rgba([255, 255, 255, *alpha]));
+        }
+        self.dirty = Some(match self.dirty {
+            Some((min, max)) => (
+                (min.0.min(x), min.1.min(y)),
+                (max.0.max(x + width), max.1.max(y + height)),
+            ),
+            None => ((x, y), (x + width, y + height)),
+        });
+        Some((x, y))
+    }
+}
+
+/// Loaded fonts sharing one dynamic glyph atlas
+pub struct Fonts {
+    id: u32,
+    fonts: Vec<Font>,
+    glyphs: HashMap<(FontId, char, u32), Option<Glyph>>,
+    atlas: GlyphAtlas,
+    texture: Option<Rc<Texture>>,
+}
+
+impl Fonts {
+    pub fn new() -> Self {
+        static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            fonts: Vec::new(),
+            glyphs: HashMap::new(),
+            atlas: GlyphAtlas::new(),
+            texture: None,
+        }
+    }
+
+    /// Load a TTF or OTF file
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<FontId, FontError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|source| FontError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let font =
+            Font::from_bytes(data, FontSettings::default()).map_err(|reason| FontError::Parse {
+                path: path.to_path_buf(),
+                reason,
+            })?;
+        self.fonts.push(font);
+        Ok(FontId {
+            owner: self.id,
+            index: self.fonts.len() - 1,
+        })
+    }
+
+    /// Size of `text` in rasterized pixels, before `TextStyle::scale`
+    pub fn measure(
+        &mut self,
+        font: FontId,
+        text: &str,
+        style: &TextStyle,
+    ) -> Result<Vec2, FontError> {
+        let (_, size) = self.layout(font, text, style)?;
+        Ok(size)
+    }
+
+    /// Queue `text` on `batch` with its first line's top at `position`, returning the
+    /// drawn size in view units
+    pub fn draw(
+        &mut self,
+        renderer: &Renderer,
+        batch: &mut SpriteBatch,
+        font: FontId,
+        text: &str,
+        position: Vec2,
+        style: &TextStyle,
+    ) -> Result<Vec2, FontError> {
+        let (glyphs, size) = self.layout(font, text, style)?;
+        let texture = self.texture(renderer)?;
+        let atlas_size = Vec2::new(texture.width() as f32, texture.height() as f32);
+
+        for placed in glyphs {
+            let Glyph {
+                position: (x, y),
+                size: (width, height),
+                ..
+            } = placed.glyph;
+            let uv_min = Vec2::new(x as f32, y as f32) / atlas_size;
+            let uv_max = Vec2::new((x + width) as f32, (y + height) as f32) / atlas_size;
+
+            let sprite = Sprite::new(
+                position + placed.position * style.scale,
+                Vec2::new(width as f32, height as f32) * style.scale,
+            )
+            .with_origin(Vec2::ZERO)
+            .with_uv(uv_min, uv_max)
+            .with_color(style.color)
+            .with_layer(style.layer)
+            .with_depth(style.depth);
+            batch.draw(&texture, sprite);
+        }
+
+        Ok(size * style.scale)
+    }
+
+    /// The atlas texture with every glyph rasterized so far. Only the texels added since
+    /// the last call are uploaded, the texture is reallocated when the atlas has grown.
+    fn texture(&mut self, renderer: &Renderer) -> Result<Rc<Texture>, FontError> {
+        let (width, height) = self.atlas.image.dimensions();
+        let texture = match &self.texture {
+            Some(texture) if (texture.width(), texture.height()) == (width, height) => {
+                if let Some(((x, y), (max_x, max_y))) = self.atlas.dirty {
+                    let region =
+                        image::imageops::crop_imm(&self.atlas.image, x, y, max_x - x, max_y - y);
+                    texture.update(renderer, x, y, &region.to_image())?;
+                }
+                Rc::clone(texture)
+            }
+            _ => {
+                let options = TextureOptions::default()
+                    .with_mipmaps(false)
+                    .with_wrap(Wrap::Clamp);
+                let texture = Rc::new(Texture::dynamic(renderer, width, height, options)?);
+                texture.update(renderer, 0, 0, &self.atlas.image)?;
+                self.texture = Some(Rc::clone(&texture));
+                texture
+            }
+        };
+
+        self.atlas.dirty = None;
+        Ok(texture)
+    }
+
+    /// Place every glyph of `text`, returning them with the size of the whole block
+    fn layout(
+        &mut self,
+        font: FontId,
+        text: &str,
+        style: &TextStyle,
+    ) -> Result<(Vec<PlacedGlyph>, Vec2), FontError> {
+        // Fonts are never removed, so once the id is checked the helpers below can index
+        if font.owner != self.id || font.index >= self.fonts.len() {
+            return Err(FontError::UnknownFont(font.index));
+        }
+        let metrics = match self.fonts[font.index].horizontal_line_metrics(style.size) {
+            Some(metrics) => metrics,
+            None => return Ok((Vec::new(), Vec2::ZERO)),
+        };
+        let line_step = metrics.new_line_size * style.line_height;
+
+        let lines: Vec<&str> = text
+            .split('\n')
+            .flat_map(|paragraph| self.wrap(font, paragraph, style))
+            .collect();
+
+        let mut placed = Vec::new();
+        let mut size = Vec2::ZERO;
+        for (index, line) in lines.iter().enumerate() {
+            let width = self.line_width(font, line, style.size);
+            let start = match style.align {
+                Align::Left => 0.0,
+                Align::Center => -width * 0.5,
+                Align::Right => -width,
+            };
+            let baseline = index as f32 * line_step + metrics.ascent;
+
+            let mut pen = start;
+            let mut previous = None;
+            for character in line.chars() {
+                if let Some(previous) = previous {
+                    pen += self.kern(font, previous, character, style.size);
+                }
+                if let Some(glyph) = self.glyph(font, character, style.size) {
+                    placed.push(PlacedGlyph {
+                        glyph,
+                        position: Vec2::new(pen, baseline) + glyph.offset,
+                    });
+                }
+                pen += self.fonts[font.index]
+                    .metrics(character, style.size)
+                    .advance_width;
+                previous = Some(character);
+            }
+
+            size.x = size.x.max(width);
+        }
+        size.y = lines.len() as f32 * line_step;
+
+        Ok((placed, size))
+    }
+
+    /// Split a paragraph into lines no wider than `style.max_width`, breaking at spaces.
+    /// Words wider than the limit get a line of their own.
+    fn wrap<'t>(&self, font: FontId, paragraph: &'t str, style: &TextStyle) -> Vec<&'t str> {
+        let max_width = match style.max_width {
+            Some(max_width) => max_width,
+            None => return vec![paragraph],
+        };
+
+        let mut lines = Vec::new();
+        let mut start = 0;
+        let mut end = 0;
+        for (index, _) in paragraph.match_indices(' ').chain([(paragraph.len(), "")]) {
+            let candidate = &paragraph[start..index];
+            if end > start && self.line_width(font, candidate, style.size) > max_width {
+                lines.push(&paragraph[start..end]);
+                start = end + 1;
+            }
+            end = index;
+        }
+        lines.push(&paragraph[start.min(paragraph.len())..]);
+
+        lines
+    }
+
+    fn line_width(&self, font: FontId, line: &str, size: f32) -> f32 {
+        let font_data = &self.fonts[font.index];
+        let mut width = 0.0;
+        let mut previous = None;
+        for character in line.chars() {
+            if let Some(previous) = previous {
+                width += self.kern(font, previous, character, size);
+            }
+            width += font_data.metrics(character, size).advance_width;
+            previous = Some(character);
+        }
+        width
+    }
+
+    fn kern(&self, font: FontId, left: char, right: char, size: f32) -> f32 {
+        self.fonts[font.index]
+            .horizontal_kern(left, right, size)
+            .unwrap_or(0.0)
+    }
+
+    /// Rasterize `character` into the atlas on first use, `None` for blank glyphs or when
+    /// the atlas is full
+    fn glyph(&mut self, font: FontId, character: char, size: f32) -> Option<Glyph> {
+        let key = (font, character, size.to_bits());
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return *glyph;
+        }
+
+        let (metrics, coverage) = self.fonts[font.index].rasterize(character, size);
+        let glyph = match metrics.width * metrics.height {
+            0 => None,
+            _ => {
+                let (width, height) = (metrics.width as u32, metrics.height as u32);
+                self.atlas
+                    .insert(width, height, &coverage)
+                    .map(|position| Glyph {
+                        position,
+                        size: (width, height),
+                        // fontdue measures ymin up from the baseline to the bitmap's bottom
+                        offset: Vec2::new(
+                            metrics.xmin as f32,
+                            -(metrics.ymin as f32 + metrics.height as f32),
+                        ),
+                    })
+            }
+        };
+
+        self.glyphs.insert(key, glyph);
+        glyph
+    }
+}
+
+impl Default for Fonts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_from_other_fonts_are_rejected() {
+        let mut fonts = Fonts::new();
+        let other = Fonts::new();
+        let foreign = FontId {
+            owner: other.id,
+            index: 0,
+        };
+
+        assert!(matches!(
+            fonts.measure(foreign, "text", &TextStyle::new(16.0)),
+            Err(FontError::UnknownFont(0))
+        ));
+    }
+
+    #[test]
+    fn atlas_tracks_the_changed_region() {
+        let mut atlas = GlyphAtlas::new();
+        assert_eq!(atlas.insert(2, 3, &[255; 6]), Some((PADDING, PADDING)));
+        assert_eq!(atlas.insert(4, 1, &[255; 4]), Some((PADDING + 3, PADDING)));
+        assert_eq!(atlas.dirty, Some(((1, 1), (8, 4))));
+        assert_eq!(atlas.image.get_pixel(2, 3).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn growing_keeps_glyph_positions() {
+        let mut atlas = GlyphAtlas::new();
+        let first = atlas.insert(8, 8, &[128; 64]).unwrap();
+        let large = INITIAL_ATLAS_SIZE - PADDING;
+        assert!(atlas
+            .insert(large, large, &vec![0; (large * large) as usize])
+            .is_some());
+
+        assert_eq!(atlas.image.width(), INITIAL_ATLAS_SIZE * 2);
+        assert_eq!(atlas.image.get_pixel(first.0, first.1).0[3], 128);
+    }
+}
@@ -0,0 +1,325 @@
+use crate::event::Event;
+use glam::Vec2;
+use glfw::{Action, GamepadAxis, GamepadButton, Glfw, JoystickId};
+
+pub const DEFAULT_DEADZONE: f32 = 0.15;
+
+const JOYSTICKS: [JoystickId; 16] = [
+    JoystickId::Joystick1,
+    JoystickId::Joystick2,
+    JoystickId::Joystick3,
+    JoystickId::Joystick4,
+    JoystickId::Joystick5,
+    JoystickId::Joystick6,
+    JoystickId::Joystick7,
+    JoystickId::Joystick8,
+    JoystickId::Joystick9,
+    JoystickId::Joystick10,
+    JoystickId::Joystick11,
+    JoystickId::Joystick12,
+    JoystickId::Joystick13,
+    JoystickId::Joystick14,
+    JoystickId::Joystick15,
+    JoystickId::Joystick16,
+];
+
+/// Every button of the standard gamepad layout
+pub const BUTTONS: [GamepadButton; 15] = [
+    GamepadButton::ButtonA,
+    GamepadButton::ButtonB,
+    GamepadButton::ButtonX,
+    GamepadButton::ButtonY,
+    GamepadButton::ButtonLeftBumper,
+    GamepadButton::ButtonRightBumper,
+    GamepadButton::ButtonBack,
+    GamepadButton::ButtonStart,
+    GamepadButton::ButtonGuide,
+    GamepadButton::ButtonLeftThumb,
+    GamepadButton::ButtonRightThumb,
+    GamepadButton::ButtonDpadUp,
+    GamepadButton::ButtonDpadRight,
+    GamepadButton::ButtonDpadDown,
+    GamepadButton::ButtonDpadLeft,
+];
+
+/// Every axis of the standard gamepad layout
+pub const AXES: [GamepadAxis; 6] = [
+    GamepadAxis::AxisLeftX,
+    GamepadAxis::AxisLeftY,
+    GamepadAxis::AxisRightX,
+    GamepadAxis::AxisRightY,
+    GamepadAxis::AxisLeftTrigger,
+    GamepadAxis::AxisRightTrigger,
+];
+
+/// A connected joystick with a standard gamepad mapping
+#[derive(Clone, Debug)]
+pub struct Gamepad {
+    id: JoystickId,
+    name: String,
+    guid: Option<String>,
+    buttons: [bool; BUTTONS.len()],
+    axes: [f32; AXES.len()],
+}
+
+impl Gamepad {
+    pub fn id(&self) -> JoystickId {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// SDL compatible GUID, used to match entries in a gamecontrollerdb
+    pub fn guid(&self) -> Option<&str> {
+        self.guid.as_deref()
+    }
+
+    pub fn button(&self, button: GamepadButton) -> bool {
+        self.buttons[button as usize]
+    }
+
+    /// Axis value after the deadzone, sticks are `-1.0..=1.0` and triggers `0.0..=1.0`
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes[axis as usize]
+    }
+
+    pub fn left_stick(&self) -> Vec2 {
+        Vec2::new(
+            self.axis(GamepadAxis::AxisLeftX),
+            self.axis(GamepadAxis::AxisLeftY),
+        )
+    }
+
+    pub fn right_stick(&self) -> Vec2 {
+        Vec2::new(
+            self.axis(GamepadAxis::AxisRightX),
+            self.axis(GamepadAxis::AxisRightY),
+        )
+    }
+}
+
+/// Tracks connected gamepads by polling glfw once per frame
+#[derive(Clone, Debug)]
+pub struct Gamepads {
+    gamepads: Vec<Gamepad>,
+    deadzone: f32,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        Self {
+            gamepads: Vec::new(),
+            deadzone: DEFAULT_DEADZONE,
+        }
+    }
+
+    pub fn deadzone(&self) -> f32 {
+        self.deadzone
+    }
+
+    /// Stick and trigger input below `deadzone` is reported as zero
+    pub fn set_deadzone(&mut self, deadzone: f32) {
+        self.deadzone = deadzone.clamp(0.0, 0.99);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Gamepad> {
+        self.gamepads.iter()
+    }
+
+    pub fn get(&self, id: JoystickId) -> Option<&Gamepad> {
+        self.gamepads.iter().find(|gamepad| gamepad.id == id)
+    }
+
+    /// The first connected gamepad, convenient for single player games
+    pub fn first(&self) -> Option<&Gamepad> {
+        self.gamepads.first()
+    }
+
+    /// Refresh every gamepad and return connection, button and axis changes as events
+    pub fn poll(&mut self, glfw: &Glfw) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        for id in JOYSTICKS {
+            let joystick = glfw.get_joystick(id);
+            let state = match joystick.is_gamepad() {
+                true => joystick.get_gamepad_state(),
+                false => None,
+            };
+            let index = self.gamepads.iter().position(|gamepad| gamepad.id == id);
+
+            let state = match (state, index) {
+                (Some(state), _) => state,
+                (None, Some(index)) => {
+                    self.gamepads.remove(index);
+                    events.push(Event::GamepadDisconnected { id });
+                    continue;
+                }
+                (None, None) => continue,
+            };
+
+            let index = match index {
+                Some(index) => index,
+                None => {
+                    let name = joystick
+                        .get_gamepad_name()
+                        .or_else(|| joystick.get_name())
+                        .unwrap_or_default();
+                    events.push(Event::GamepadConnected {
+                        id,
+                        name: name.clone(),
+                    });
+                    self.gamepads.push(Gamepad {
+                        id,
+                        name,
+                        guid: joystick.get_guid(),
+                        buttons: [false; BUTTONS.len()],
+                        axes: [0.0; AXES.len()],
+                    });
+                    self.gamepads.len() - 1
+                }
+            };
+
+            let mut axes = [0.0; AXES.len()];
+            for axis in AXES {
+                axes[axis as usize] = state.get_axis(axis);
+            }
+            let axes = apply_deadzone(axes, self.deadzone);
+
+            let gamepad = &mut self.gamepads[index];
+            for button in BUTTONS {
+                let pressed = state.get_button_state(button) == Action::Press;
+                if gamepad.buttons[button as usize] != pressed {
+                    gamepad.buttons[button as usize] = pressed;
+                    events.push(Event::GamepadButton {
+                        id,
+                        button,
+                        action: if pressed {
+                            Action::Press
+                        } else {
+                            Action::Release
+                        },
+                    });
+                }
+            }
+            for axis in AXES {
+                let value = axes[axis as usize];
+                if gamepad.axes[axis as usize] != value {
+                    gamepad.axes[axis as usize] = value;
+                    events.push(Event::GamepadAxis { id, axis, value });
+                }
+            }
+        }
+
+        events
+    }
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Apply a radial deadzone to both sticks and remap triggers from glfw's `-1.0..=1.0`
+/// to `0.0..=1.0` before applying the deadzone to them
+fn apply_deadzone(mut axes: [f32; AXES.len()], deadzone: f32) -> [f32; AXES.len()] {
+    let rescale = |magnitude: f32| match magnitude < deadzone {
+        true => 0.0,
+        false => ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0),
+    };
+
+    for (x, y) in [
+        (GamepadAxis::AxisLeftX, GamepadAxis::AxisLeftY),
+        (GamepadAxis::AxisRightX, GamepadAxis::AxisRightY),
+    ] {
+        let stick = Vec2::new(axes[x as usize], axes[y as usize]);
+        let length = stick.length();
+        let stick = match length > 0.0 {
+            true => stick / length * rescale(length),
+            false => Vec2::ZERO,
+        };
+        axes[x as usize] = stick.x;
+        axes[y as usize] = stick.y;
+    }
+
+    for trigger in [GamepadAxis::AxisLeftTrigger, GamepadAxis::AxisRightTrigger] {
+        let value = (axes[trigger as usize] + 1.0) * 0.5;
+        axes[trigger as usize] = rescale(value);
+    }
+
+    axes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT_X: usize = GamepadAxis::AxisLeftX as usize;
+    const LEFT_Y: usize = GamepadAxis::AxisLeftY as usize;
+    const LEFT_TRIGGER: usize = GamepadAxis::AxisLeftTrigger as usize;
+
+    /// Axes at rest, sticks centred and triggers released
+    fn rest() -> [f32; AXES.len()] {
+        let mut axes = [0.0; AXES.len()];
+        axes[LEFT_TRIGGER] = -1.0;
+        axes[GamepadAxis::AxisRightTrigger as usize] = -1.0;
+        axes
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn input_inside_the_deadzone_is_zero() {
+        let mut axes = rest();
+        axes[LEFT_X] = 0.1;
+        axes[LEFT_Y] = -0.1;
+        // Half pressed is 0.55 after remapping, inside a 0.6 deadzone
+        axes[LEFT_TRIGGER] = 0.1;
+
+        assert_eq!(apply_deadzone(axes, 0.6), [0.0; AXES.len()]);
+        assert_eq!(apply_deadzone(rest(), 0.2), [0.0; AXES.len()]);
+    }
+
+    #[test]
+    fn output_is_rescaled_to_reach_full_range() {
+        let mut axes = rest();
+        axes[LEFT_X] = 1.0;
+        axes[LEFT_TRIGGER] = 1.0;
+        let output = apply_deadzone(axes, 0.2);
+        assert!(close(output[LEFT_X], 1.0));
+        assert!(close(output[LEFT_TRIGGER], 1.0));
+
+        // Halfway between the deadzone edge and full deflection
+        axes[LEFT_X] = -0.6;
+        assert!(close(apply_deadzone(axes, 0.2)[LEFT_X], -0.5));
+    }
+
+    #[test]
+    fn diagonals_keep_their_direction() {
+        let mut axes = rest();
+        axes[LEFT_X] = 0.6;
+        axes[LEFT_Y] = -0.3;
+        let output = apply_deadzone(axes, 0.25);
+
+        let input = Vec2::new(0.6, -0.3);
+        let stick = Vec2::new(output[LEFT_X], output[LEFT_Y]);
+        assert!(close(stick.normalize().dot(input.normalize()), 1.0));
+        assert!(close(stick.length(), (input.length() - 0.25) / 0.75));
+    }
+
+    #[test]
+    fn overshooting_sticks_are_capped() {
+        let mut axes = rest();
+        axes[LEFT_X] = 1.0;
+        axes[LEFT_Y] = 1.0;
+        let output = apply_deadzone(axes, 0.1);
+        assert!(close(
+            Vec2::new(output[LEFT_X], output[LEFT_Y]).length(),
+            1.0
+        ));
+    }
+}
@@ -0,0 +1,698 @@
+use crate::error::InputError;
+use crate::event::Event;
+use glfw::{Action, GamepadAxis, GamepadButton, JoystickId, Key, MouseButton};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Axis magnitude above which an axis binding counts as pressed
+pub const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+
+/// A physical input that can drive an action
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Binding {
+    Key(Key),
+    MouseButton(MouseButton),
+    GamepadButton(GamepadButton),
+    /// The full `-1.0..=1.0` range of a gamepad axis
+    GamepadAxis(GamepadAxis),
+    /// One direction of a gamepad axis, reported as `0.0..=1.0`
+    GamepadHalfAxis {
+        axis: GamepadAxis,
+        positive: bool,
+    },
+    /// A pair of keys acting as an axis, such as A/D for horizontal movement
+    KeyAxis {
+        negative: Key,
+        positive: Key,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct ActionState {
+    pressed: bool,
+    just_pressed: bool,
+    just_released: bool,
+    value: f32,
+}
+
+/// Maps named actions to keys, mouse buttons and gamepad inputs
+#[derive(Clone, Debug, Default)]
+pub struct InputMap {
+    bindings: BTreeMap<String, Vec<Binding>>,
+    states: HashMap<String, ActionState>,
+    // Raw state is keyed by the glfw codes, not every glfw enum implements `Hash`
+    keys: Buttons,
+    mouse_buttons: Buttons,
+    gamepads: HashMap<i32, GamepadState>,
+}
+
+/// Raw state of one connected gamepad
+#[derive(Clone, Debug, Default)]
+struct GamepadState {
+    buttons: Buttons,
+    axes: HashMap<i32, f32>,
+}
+
+/// Held buttons plus every press since the last update, so a tap that is released before
+/// the next update still registers
+#[derive(Clone, Debug, Default)]
+struct Buttons {
+    held: HashSet<i32>,
+    pressed: HashSet<i32>,
+}
+
+impl Buttons {
+    fn set(&mut self, code: i32, action: Action) {
+        match action {
+            Action::Press => {
+                self.held.insert(code);
+                self.pressed.insert(code);
+            }
+            Action::Release => {
+                self.held.remove(&code);
+            }
+            Action::Repeat => {}
+        }
+    }
+
+    fn is_down(&self, code: i32) -> bool {
+        self.held.contains(&code) || self.pressed.contains(&code)
+    }
+
+    fn clear(&mut self) {
+        self.held.clear();
+        self.pressed.clear();
+    }
+
+    /// Forget presses that have been seen by an update
+    fn latch(&mut self) {
+        self.pressed.clear();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BindingsFile {
+    bindings: BTreeMap<String, Vec<Binding>>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `binding` to `action`, creating the action if needed
+    pub fn bind(&mut self, action: impl Into<String>, binding: Binding) -> &mut Self {
+        let bindings = self.bindings.entry(action.into()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        self
+    }
+
+    /// Replace every binding of `action`
+    pub fn rebind(&mut self, action: impl Into<String>, bindings: Vec<Binding>) {
+        self.bindings.insert(action.into(), bindings);
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(bindings) = self.bindings.get_mut(action) {
+            bindings.retain(|bound| *bound != binding);
+        }
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.bindings.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.bindings.keys().map(String::as_str)
+    }
+
+    /// Track key, mouse button and gamepad state, events are never consumed
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::Key { key, action, .. } => self.keys.set(key as i32, action),
+            Event::MouseButton { button, action, .. } => {
+                self.mouse_buttons.set(button as i32, action)
+            }
+            Event::GamepadButton { id, button, action } => {
+                self.gamepad(id).buttons.set(button as i32, action)
+            }
+            Event::GamepadAxis { id, axis, value } => self.set_gamepad_axis(id, axis, value),
+            Event::GamepadDisconnected { id } => {
+                self.gamepads.remove(&(id as i32));
+            }
+            // Keys released while unfocused never report a release
+            Event::Focused(false) => {
+                self.keys.clear();
+                self.mouse_buttons.clear();
+            }
+            _ => {}
+        }
+    }
+
+    /// Every connected gamepad drives the same actions, a button counts as held while
+    /// it is held on any of them
+    pub fn set_gamepad_button(&mut self, id: JoystickId, button: GamepadButton, pressed: bool) {
+        let action = if pressed {
+            Action::Press
+        } else {
+            Action::Release
+        };
+        self.gamepad(id).buttons.set(button as i32, action);
+    }
+
+    /// The strongest value among the connected gamepads drives axis bindings
+    pub fn set_gamepad_axis(&mut self, id: JoystickId, axis: GamepadAxis, value: f32) {
+        self.gamepad(id).axes.insert(axis as i32, value);
+    }
+
+    fn gamepad(&mut self, id: JoystickId) -> &mut GamepadState {
+        self.gamepads.entry(id as i32).or_default()
+    }
+
+    /// Recompute every action from the raw input. Call once per fixed update so each
+    /// press is reported as `just_pressed` by exactly one update.
+    pub fn update(&mut self) {
+        for (action, bindings) in &self.bindings {
+            let value = bindings
+                .iter()
+                .map(|binding| self.binding_value(*binding))
+                .fold(0.0f32, |value, binding| match binding.abs() > value.abs() {
+                    true => binding,
+                    false => value,
+                });
+            let pressed = value.abs() >= AXIS_PRESS_THRESHOLD;
+
+            let state = self.states.entry(action.clone()).or_default();
+            *state = ActionState {
+                pressed,
+                just_pressed: pressed && !state.pressed,
+                just_released: !pressed && state.pressed,
+                value,
+            };
+        }
+
+        self.states
+            .retain(|action, _| self.bindings.contains_key(action));
+        self.keys.latch();
+        self.mouse_buttons.latch();
+        for gamepad in self.gamepads.values_mut() {
+            gamepad.buttons.latch();
+        }
+    }
+
+    fn binding_value(&self, binding: Binding) -> f32 {
+        let held = |buttons: &Buttons, code: i32| match buttons.is_down(code) {
+            true => 1.0,
+            false => 0.0,
+        };
+        let key = |key: Key| held(&self.keys, key as i32);
+
+        match binding {
+            Binding::Key(bound) => key(bound),
+            Binding::MouseButton(button) => held(&self.mouse_buttons, button as i32),
+            Binding::GamepadButton(button) => match self
+                .gamepads
+                .values()
+                .any(|gamepad| gamepad.buttons.is_down(button as i32))
+            {
+                true => 1.0,
+                false => 0.0,
+            },
+            Binding::GamepadAxis(axis) => self.axis(axis),
+            Binding::GamepadHalfAxis { axis, positive } => match positive {
+                true => self.axis(axis).max(0.0),
+                false => (-self.axis(axis)).max(0.0),
+            },
+            Binding::KeyAxis { negative, positive } => key(positive) - key(negative),
+        }
+    }
+
+    fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.gamepads
+            .values()
+            .filter_map(|gamepad| gamepad.axes.get(&(axis as i32)).copied())
+            .fold(0.0, |strongest, value| {
+                match value.abs() > strongest.abs() {
+                    true => value,
+                    false => strongest,
+                }
+            })
+    }
+
+    fn state(&self, action: &str) -> ActionState {
+        self.states.get(action).copied().unwrap_or_default()
+    }
+
+    pub fn pressed(&self, action: &str) -> bool {
+        self.state(action).pressed
+    }
+
+    /// Pressed this update but not the previous one
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.state(action).just_pressed
+    }
+
+    /// Released this update after being pressed the previous one
+    pub fn just_released(&self, action: &str) -> bool {
+        self.state(action).just_released
+    }
+
+    /// Strongest value among the action's bindings, buttons report `0.0` or `1.0`
+    pub fn value(&self, action: &str) -> f32 {
+        self.state(action).value
+    }
+
+    /// Load bindings from a TOML file, replacing the current ones
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<(), InputError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| InputError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let file: BindingsFile = toml::from_str(&source)?;
+        self.bindings = file.bindings;
+        Ok(())
+    }
+
+    /// Save the current bindings as TOML
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), InputError> {
+        let path = path.as_ref();
+        let file = BindingsFile {
+            bindings: self.bindings.clone(),
+        };
+
+        std::fs::write(path, toml::to_string_pretty(&file)?).map_err(|source| InputError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Binding::Key(key) => write!(f, "key:{}", key_name(key)),
+            Binding::MouseButton(button) => write!(f, "mouse:{}", mouse_button_name(button)),
+            Binding::GamepadButton(button) => {
+                write!(f, "button:{}", gamepad_button_name(button))
+            }
+            Binding::GamepadAxis(axis) => write!(f, "axis:{}", gamepad_axis_name(axis)),
+            Binding::GamepadHalfAxis { axis, positive } => {
+                let sign = if positive { '+' } else { '-' };
+                write!(f, "axis:{}{}", sign, gamepad_axis_name(axis))
+            }
+            Binding::KeyAxis { negative, positive } => {
+                write!(f, "keys:{},{}", key_name(negative), key_name(positive))
+            }
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = InputError;
+
+    /// Parse the `kind:name` form produced by `Display`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let unknown = || InputError::UnknownBinding(value.to_string());
+        let (kind, name) = value.split_once(':').ok_or_else(unknown)?;
+
+        let binding = match kind {
+            "key" => Binding::Key(parse_key(name).ok_or_else(unknown)?),
+            "mouse" => Binding::MouseButton(parse_mouse_button(name).ok_or_else(unknown)?),
+            "button" => Binding::GamepadButton(parse_gamepad_button(name).ok_or_else(unknown)?),
+            "axis" => match name.strip_prefix('+').or_else(|| name.strip_prefix('-')) {
+                Some(axis) => Binding::GamepadHalfAxis {
+                    axis: parse_gamepad_axis(axis).ok_or_else(unknown)?,
+                    positive: name.starts_with('+'),
+                },
+                None => Binding::GamepadAxis(parse_gamepad_axis(name).ok_or_else(unknown)?),
+            },
+            "keys" => {
+                let (negative, positive) = name.split_once(',').ok_or_else(unknown)?;
+                Binding::KeyAxis {
+                    negative: parse_key(negative.trim()).ok_or_else(unknown)?,
+                    positive: parse_key(positive.trim()).ok_or_else(unknown)?,
+                }
+            }
+            _ => return Err(unknown()),
+        };
+
+        Ok(binding)
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = InputError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        binding.to_string()
+    }
+}
+
+const KEYS: &[(&str, Key)] = &[
+    ("Space", Key::Space),
+    ("Apostrophe", Key::Apostrophe),
+    ("Comma", Key::Comma),
+    ("Minus", Key::Minus),
+    ("Period", Key::Period),
+    ("Slash", Key::Slash),
+    ("0", Key::Num0),
+    ("1", Key::Num1),
+    ("2", Key::Num2),
+    ("3", Key::Num3),
+    ("4", Key::Num4),
+    ("5", Key::Num5),
+    ("6", Key::Num6),
+    ("7", Key::Num7),
+    ("8", Key::Num8),
+    ("9", Key::Num9),
+    ("Semicolon", Key::Semicolon),
+    ("Equal", Key::Equal),
+    ("A", Key::A),
+    ("B", Key::B),
+    ("C", Key::C),
+    ("D", Key::D),
+    ("E", Key::E),
+    ("F", Key::F),
+    ("G", Key::G),
+    ("H", Key::H),
+    ("I", Key::I),
+    ("J", Key::J),
+    ("K", Key::K),
+    ("L", Key::L),
+    ("M", Key::M),
+    ("N", Key::N),
+    ("O", Key::O),
+    ("P", Key::P),
+    ("Q", Key::Q),
+    ("R", Key::R),
+    ("S", Key::S),
+    ("T", Key::T),
+    ("U", Key::U),
+    ("V", Key::V),
+    ("W", Key::W),
+    ("X", Key::X),
+    ("Y", Key::Y),
+    ("Z", Key::Z),
+    ("LeftBracket", Key::LeftBracket),
+    ("Backslash", Key::Backslash),
+    ("RightBracket", Key::RightBracket),
+    ("GraveAccent", Key::GraveAccent),
+    ("World1", Key::World1),
+    ("World2", Key::World2),
+    ("Escape", Key::Escape),
+    ("Enter", Key::Enter),
+    ("Tab", Key::Tab),
+    ("Backspace", Key::Backspace),
+    ("Insert", Key::Insert),
+    ("Delete", Key::Delete),
+    ("Right", Key::Right),
+    ("Left", Key::Left),
+    ("Down", Key::Down),
+    ("Up", Key::Up),
+    ("PageUp", Key::PageUp),
+    ("PageDown", Key::PageDown),
+    ("Home", Key::Home),
+    ("End", Key::End),
+    ("CapsLock", Key::CapsLock),
+    ("ScrollLock", Key::ScrollLock),
+    ("NumLock", Key::NumLock),
+    ("PrintScreen", Key::PrintScreen),
+    ("Pause", Key::Pause),
+    ("F1", Key::F1),
+    ("F2", Key::F2),
+    ("F3", Key::F3),
+    ("F4", Key::F4),
+    ("F5", Key::F5),
+    ("F6", Key::F6),
+    ("F7", Key::F7),
+    ("F8", Key::F8),
+    ("F9", Key::F9),
+    ("F10", Key::F10),
+    ("F11", Key::F11),
+    ("F12", Key::F12),
+    ("F13", Key::F13),
+    ("F14", Key::F14),
+    ("F15", Key::F15),
+    ("F16", Key::F16),
+    ("F17", Key::F17),
+    ("F18", Key::F18),
+    ("F19", Key::F19),
+    ("F20", Key::F20),
+    ("F21", Key::F21),
+    ("F22", Key::F22),
+    ("F23", Key::F23),
+    ("F24", Key::F24),
+    ("F25", Key::F25),
+    ("Kp0", Key::Kp0),
+    ("Kp1", Key::Kp1),
+    ("Kp2", Key::Kp2),
+    ("Kp3", Key::Kp3),
+    ("Kp4", Key::Kp4),
+    ("Kp5", Key::Kp5),
+    ("Kp6", Key::Kp6),
+    ("Kp7", Key::Kp7),
+    ("Kp8", Key::Kp8),
+    ("Kp9", Key::Kp9),
+    ("KpDecimal", Key::KpDecimal),
+    ("KpDivide", Key::KpDivide),
+    ("KpMultiply", Key::KpMultiply),
+    ("KpSubtract", Key::KpSubtract),
+    ("KpAdd", Key::KpAdd),
+    ("KpEnter", Key::KpEnter),
+    ("KpEqual", Key::KpEqual),
+    ("LeftShift", Key::LeftShift),
+    ("LeftControl", Key::LeftControl),
+    ("LeftAlt", Key::LeftAlt),
+    ("LeftSuper", Key::LeftSuper),
+    ("RightShift", Key::RightShift),
+    ("RightControl", Key::RightControl),
+    ("RightAlt", Key::RightAlt),
+    ("RightSuper", Key::RightSuper),
+    ("Menu", Key::Menu),
+    ("Unknown", Key::Unknown),
+];
+
+const MOUSE_BUTTONS: &[(&str, MouseButton)] = &[
+    ("Left", MouseButton::Button1),
+    ("Right", MouseButton::Button2),
+    ("Middle", MouseButton::Button3),
+    ("4", MouseButton::Button4),
+    ("5", MouseButton::Button5),
+    ("6", MouseButton::Button6),
+    ("7", MouseButton::Button7),
+    ("8", MouseButton::Button8),
+];
+
+const GAMEPAD_BUTTONS: &[(&str, GamepadButton)] = &[
+    ("A", GamepadButton::ButtonA),
+    ("B", GamepadButton::ButtonB),
+    ("X", GamepadButton::ButtonX),
+    ("Y", GamepadButton::ButtonY),
+    ("LeftBumper", GamepadButton::ButtonLeftBumper),
+    ("RightBumper", GamepadButton::ButtonRightBumper),
+    ("Back", GamepadButton::ButtonBack),
+    ("Start", GamepadButton::ButtonStart),
+    ("Guide", GamepadButton::ButtonGuide),
+    ("LeftThumb", GamepadButton::ButtonLeftThumb),
+    ("RightThumb", GamepadButton::ButtonRightThumb),
+    ("DpadUp", GamepadButton::ButtonDpadUp),
+    ("DpadRight", GamepadButton::ButtonDpadRight),
+    ("DpadDown", GamepadButton::ButtonDpadDown),
+    ("DpadLeft", GamepadButton::ButtonDpadLeft),
+];
+
+const GAMEPAD_AXES: &[(&str, GamepadAxis)] = &[
+    ("LeftX", GamepadAxis::AxisLeftX),
+    ("LeftY", GamepadAxis::AxisLeftY),
+    ("RightX", GamepadAxis::AxisRightX),
+    ("RightY", GamepadAxis::AxisRightY),
+    ("LeftTrigger", GamepadAxis::AxisLeftTrigger),
+    ("RightTrigger", GamepadAxis::AxisRightTrigger),
+];
+
+fn find_name<T: PartialEq + fmt::Debug>(table: &[(&'static str, T)], value: T) -> String {
+    table
+        .iter()
+        .find(|(_, entry)| *entry == value)
+        .map_or_else(|| format!("{:?}", value), |(name, _)| name.to_string())
+}
+
+fn find_value<T: Copy>(table: &[(&str, T)], name: &str) -> Option<T> {
+    table
+        .iter()
+        .find(|(entry, _)| entry.eq_ignore_ascii_case(name))
+        .map(|(_, value)| *value)
+}
+
+pub fn key_name(key: Key) -> String {
+    find_name(KEYS, key)
+}
+
+pub fn parse_key(name: &str) -> Option<Key> {
+    find_value(KEYS, name)
+}
+
+fn mouse_button_name(button: MouseButton) -> String {
+    find_name(MOUSE_BUTTONS, button)
+}
+
+fn parse_mouse_button(name: &str) -> Option<MouseButton> {
+    find_value(MOUSE_BUTTONS, name)
+}
+
+pub fn gamepad_button_name(button: GamepadButton) -> String {
+    find_name(GAMEPAD_BUTTONS, button)
+}
+
+fn parse_gamepad_button(name: &str) -> Option<GamepadButton> {
+    find_value(GAMEPAD_BUTTONS, name)
+}
+
+pub fn gamepad_axis_name(axis: GamepadAxis) -> String {
+    find_name(GAMEPAD_AXES, axis)
+}
+
+fn parse_gamepad_axis(name: &str) -> Option<GamepadAxis> {
+    find_value(GAMEPAD_AXES, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_table_covers_every_glfw_key() {
+        // glfw defines 120 named keys plus `Key::Unknown`
+        assert_eq!(KEYS.len(), 121);
+
+        let codes: HashSet<i32> = KEYS.iter().map(|(_, key)| *key as i32).collect();
+        assert_eq!(codes.len(), KEYS.len());
+        let names: HashSet<String> = KEYS
+            .iter()
+            .map(|(name, _)| name.to_ascii_lowercase())
+            .collect();
+        assert_eq!(names.len(), KEYS.len());
+    }
+
+    #[test]
+    fn bindings_parse_their_display_form() {
+        let bindings = [
+            Binding::Key(Key::KpMultiply),
+            Binding::MouseButton(MouseButton::Button3),
+            Binding::GamepadButton(GamepadButton::ButtonDpadLeft),
+            Binding::GamepadAxis(GamepadAxis::AxisRightY),
+            Binding::GamepadHalfAxis {
+                axis: GamepadAxis::AxisLeftTrigger,
+                positive: false,
+            },
+            Binding::KeyAxis {
+                negative: Key::LeftSuper,
+                positive: Key::F25,
+            },
+        ];
+        for binding in bindings {
+            assert_eq!(binding.to_string().parse::<Binding>().unwrap(), binding);
+        }
+        assert!("key:Hyper".parse::<Binding>().is_err());
+    }
+
+    fn press(key: Key, action: Action) -> Event {
+        Event::Key {
+            key,
+            scancode: 0,
+            action,
+            modifiers: glfw::Modifiers::empty(),
+        }
+    }
+
+    #[test]
+    fn just_pressed_is_reported_by_one_update() {
+        let mut input = InputMap::new();
+        input.bind("jump", Binding::Key(Key::Space));
+
+        input.handle_event(&press(Key::Space, Action::Press));
+        input.update();
+        assert!(input.just_pressed("jump"));
+        input.update();
+        assert!(input.pressed("jump"));
+        assert!(!input.just_pressed("jump"));
+    }
+
+    #[test]
+    fn taps_between_updates_are_latched() {
+        let mut input = InputMap::new();
+        input.bind("jump", Binding::Key(Key::Space));
+
+        input.handle_event(&press(Key::Space, Action::Press));
+        input.handle_event(&press(Key::Space, Action::Release));
+        input.update();
+        assert!(input.just_pressed("jump"));
+        input.update();
+        assert!(input.just_released("jump"));
+        assert!(!input.pressed("jump"));
+    }
+
+    #[test]
+    fn gamepads_are_tracked_separately() {
+        let mut input = InputMap::new();
+        input.bind("jump", Binding::GamepadButton(GamepadButton::ButtonA));
+        input.bind("move", Binding::GamepadAxis(GamepadAxis::AxisLeftX));
+
+        input.set_gamepad_button(JoystickId::Joystick1, GamepadButton::ButtonA, true);
+        input.set_gamepad_button(JoystickId::Joystick2, GamepadButton::ButtonA, true);
+        input.set_gamepad_axis(JoystickId::Joystick1, GamepadAxis::AxisLeftX, -0.8);
+        input.set_gamepad_axis(JoystickId::Joystick2, GamepadAxis::AxisLeftX, 0.3);
+        input.update();
+        assert!(input.pressed("jump"));
+        assert_eq!(input.value("move"), -0.8);
+
+        // Releasing or unplugging one pad leaves the other's input alone
+        input.set_gamepad_button(JoystickId::Joystick2, GamepadButton::ButtonA, false);
+        input.handle_event(&Event::GamepadDisconnected {
+            id: JoystickId::Joystick1,
+        });
+        input.update();
+        assert!(!input.pressed("jump"));
+        assert_eq!(input.value("move"), 0.3);
+
+        input.set_gamepad_button(JoystickId::Joystick2, GamepadButton::ButtonA, true);
+        input.handle_event(&Event::GamepadDisconnected {
+            id: JoystickId::Joystick1,
+        });
+        input.update();
+        assert!(input.pressed("jump"));
+    }
+
+    #[test]
+    fn every_key_survives_save_and_load() {
+        let mut input = InputMap::new();
+        for (name, key) in KEYS {
+            input.bind(*name, Binding::Key(*key));
+        }
+
+        let path = std::env::temp_dir().join(format!("bindings-{}.toml", std::process::id()));
+        input.save(&path).unwrap();
+        let mut loaded = InputMap::new();
+        let result = loaded.load(&path);
+        std::fs::remove_file(&path).unwrap();
+        result.unwrap();
+
+        for (name, key) in KEYS {
+            assert_eq!(loaded.bindings(name), [Binding::Key(*key)]);
+        }
+    }
+}
@@ -1,80 +1,161 @@
-use application::{Application, WindowMetadata};
-use bgfx_rs::static_lib::{ClearFlags, DbgTextClearArgs, DebugFlags, ResetArgs, SetViewClearArgs};
+use application::Application;
+use bgfx_rs::static_lib::ClearFlags;
+use cli::{Args, Command};
+use config::Config;
+use debug_text::{AnsiBuilder, Attr, Color, DebugText};
 use error::Result;
-use glfw::{Context, WindowMode};
+use scene::{Scene, Transition};
+use std::process::ExitCode;
+use view::{Pass, Stage};
 
 mod application;
+mod camera;
+mod cli;
+mod config;
+mod console;
+mod debug_text;
+mod display;
 mod error;
+mod event;
+mod font;
+mod gamepad;
+mod input;
+mod platform;
+mod quad;
+mod renderer;
+mod scene;
+mod shader;
+mod sprite;
+mod texture;
 mod tile;
+mod timestep;
+mod view;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            // Most messages already embed their cause, only print the ones that don't
+            let mut printed = error.to_string();
+            let mut source = std::error::Error::source(&error);
+            while let Some(cause) = source {
+                let message = cause.to_string();
+                if !printed.contains(&message) {
+                    eprintln!("  caused by: {}", message);
+                    printed.push_str(&message);
+                }
+                source = cause.source();
+            }
+            if let error::Error::Cli(_) = error {
+                eprint!("\n{}", cli::USAGE);
+            }
+
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
 
-const TITLE: &str = "Test window";
-const WIDTH: u32 = 1280;
-const HEIGHT: u32 = 720;
-
-fn main() -> Result<()> {
-    let metadata =
-        WindowMetadata::new(TITLE, WIDTH, HEIGHT, WindowMode::Windowed, DebugFlags::TEXT);
+fn run() -> Result<()> {
+    let args = match Args::parse(std::env::args().skip(1))? {
+        Command::Run(args) => args,
+        Command::Help => {
+            print!("{}", cli::USAGE);
+            return Ok(());
+        }
+    };
+
+    let path = match &args.config {
+        Some(path) => path.clone(),
+        None => std::env::var(config::PATH_ENV)
+            .unwrap_or_else(|_| config::DEFAULT_PATH.into())
+            .into(),
+    };
+    let mut config = match args.config {
+        // An explicitly requested config file has to exist
+        Some(_) => Config::load(&path)?,
+        None => Config::load_or_default(&path)?,
+    };
+    // The file as loaded, runtime changes are merged into this and written back on exit
+    let mut saved = config.clone();
+    config.apply_env()?;
+    args.apply(&mut config)?;
+
+    let mut metadata = config.window_metadata();
+    if args.frames.is_some() {
+        metadata = metadata.with_frame_limit(args.frames);
+    }
     let mut application = Application::try_new(metadata)?;
 
     application.init()?;
-    application.run(&executor)
-}
 
-fn executor(app: &mut Application) -> crate::error::Result<()> {
-    app.window.make_current();
-    app.window.set_key_polling(true);
-
-    bgfx_rs::static_lib::set_debug(app.debug_flags.bits());
-    bgfx_rs::static_lib::set_view_clear(
-        0,
-        ClearFlags::COLOR.bits() | ClearFlags::DEPTH.bits(),
-        SetViewClearArgs {
-            // rgba: 0x103030ff,
-            rgba: 0x443355FF,
-            ..Default::default()
-        },
-    );
-
-    loop {
-        match app.window.should_close() {
-            true => break,
-            false => tick(app),
-        }
+    application.run(DebugTextScene)?;
+
+    let mut current = config.clone();
+    application.store_settings(&mut current);
+    if saved.merge_changes(&config, &current) {
+        saved.save(&path)?;
     }
 
-    bgfx_rs::static_lib::shutdown();
     Ok(())
 }
 
-fn tick(app: &mut Application) {
-    // Swap front and back buffers
-    // self.window.swap_buffers();
+/// Demo scene printing colored debug text
+struct DebugTextScene;
 
-    // Poll for and process events
-    app.handle_events();
-
-    let size = app.window.get_framebuffer_size();
-    let size = (size.0 as u32, size.1 as u32);
-
-    if app.size != size {
-        bgfx_rs::static_lib::reset(size.0, size.1, ResetArgs::default());
-        app.size = size;
+impl Scene for DebugTextScene {
+    fn on_enter(&mut self, app: &mut Application) -> Result<()> {
+        if let Some(renderer) = app.renderer() {
+            renderer.set_debug(app.debug_flags);
+        }
+        // 0x103030ff
+        app.views.add(
+            Pass::new("world", Stage::World)
+                .with_clear(ClearFlags::COLOR | ClearFlags::DEPTH, 0x443355FF),
+        );
+        Ok(())
     }
 
-    bgfx_rs::static_lib::set_view_rect(0, 0, 0, size.0 as u16, size.1 as u16);
-    bgfx_rs::static_lib::touch(0);
-
-    bgfx_rs::static_lib::dbg_text_clear(DbgTextClearArgs::default());
+    fn update(&mut self, _app: &mut Application, _dt: f64) -> Result<Transition> {
+        Ok(Transition::None)
+    }
 
-    bgfx_rs::static_lib::dbg_text(0, 1, 0x0f, "Color can be changed with ANSI \x1b[9;me\x1b[10;ms\x1b[11;mc\x1b[12;ma\x1b[13;mp\x1b[14;me\x1b[0m code too.");
-    bgfx_rs::static_lib::dbg_text(80, 1, 0x0f, "\x1b[;0m    \x1b[;1m    \x1b[; 2m    \x1b[; 3m    \x1b[; 4m    \x1b[; 5m    \x1b[; 6m    \x1b[; 7m    \x1b[0m");
-    bgfx_rs::static_lib::dbg_text(80, 2, 0x0f, "\x1b[;8m    \x1b[;9m    \x1b[;10m    \x1b[;11m    \x1b[;12m    \x1b[;13m    \x1b[;14m    \x1b[;15m    \x1b[0m");
-    bgfx_rs::static_lib::dbg_text(
-        0,
-        4,
-        0x3f,
-        "Description: Initialization and debug text with bgfx-rs Rust API.",
-    );
+    fn render(&mut self, app: &mut Application, _alpha: f32) -> Result<()> {
+        let renderer = match app.renderer() {
+            Some(renderer) => renderer,
+            None => return Ok(()),
+        };
+        renderer.dbg_text_clear();
+
+        let mut text = DebugText::new(0, 1);
+        let mut colors = AnsiBuilder::new().text("Color can be changed with ANSI ");
+        for (letter, color) in "escape".chars().zip(&Color::ALL[9..]) {
+            colors = colors.fore(*color, &letter.to_string());
+        }
+        text.print(renderer, &colors.text(" code too.").build())?;
+
+        // Every background color as a four cell swatch, two rows of eight
+        for (row, swatches) in Color::ALL.chunks(8).enumerate() {
+            let line = swatches
+                .iter()
+                .fold(AnsiBuilder::new(), |line, color| line.back(*color, "    "))
+                .build();
+            text.move_to(80, 1 + row as u16);
+            text.print(renderer, &line)?;
+        }
 
-    bgfx_rs::static_lib::frame(false);
+        text.move_to(0, 4);
+        text.attr = Attr::new(Color::White, Color::Cyan);
+        text.println(
+            renderer,
+            "Description: Initialization and debug text with bgfx-rs Rust API.",
+        )?;
+        text.attr = Attr::DEFAULT;
+        text.println(
+            renderer,
+            &format!("Renderer: {:?}", renderer.renderer_type()),
+        )?;
+
+        Ok(())
+    }
 }
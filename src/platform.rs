@@ -0,0 +1,171 @@
+use crate::error::InitializationError;
+use bgfx_rs::static_lib::{Init, PlatformData};
+use glam::Vec2;
+use raw_window_handle::RawWindowHandle;
+use std::ffi::c_void;
+use std::ptr;
+
+/// Backbuffer size in pixels, with named fields so width and height can't be transposed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Resolution {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    pub fn as_vec2(self) -> Vec2 {
+        Vec2::new(self.width as f32, self.height as f32)
+    }
+
+    /// Width over height, `0` height is treated as `1` so the ratio stays finite
+    pub fn aspect_ratio(self) -> f32 {
+        self.width as f32 / self.height.max(1) as f32
+    }
+
+    /// Write this resolution into the bgfx init parameters
+    pub fn apply(self, init: &mut Init) {
+        init.resolution.width = self.width;
+        init.resolution.height = self.height;
+    }
+}
+
+/// The pointers bgfx expects in `PlatformData` for a given window
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NativeHandles {
+    /// `PlatformData::ndt`, the display connection where the platform has one
+    pub display: *mut c_void,
+    /// `PlatformData::nwh`, the window or surface rendered to
+    pub window: *mut c_void,
+}
+
+impl NativeHandles {
+    pub fn platform_data(self) -> PlatformData {
+        let mut pd = PlatformData::new();
+        pd.ndt = self.display;
+        pd.nwh = self.window;
+        pd
+    }
+}
+
+/// Map a raw window handle to the display and window pointers bgfx renders to
+pub fn native_handles(handle: RawWindowHandle) -> Result<NativeHandles, InitializationError> {
+    let (display, window) = match handle {
+        RawWindowHandle::Xlib(data) => (data.display, data.window as *mut c_void),
+        RawWindowHandle::Xcb(data) => (data.connection, data.window as usize as *mut c_void),
+        // Wayland has no windows, bgfx renders to the surface
+        RawWindowHandle::Wayland(data) => (data.display, data.surface),
+        RawWindowHandle::AppKit(data) => (ptr::null_mut(), data.ns_window),
+        RawWindowHandle::Win32(data) => (ptr::null_mut(), data.hwnd),
+        RawWindowHandle::WinRt(data) => (ptr::null_mut(), data.core_window),
+        handle => {
+            return Err(InitializationError::UnsupportedWindowHandle(handle_kind(
+                &handle,
+            )))
+        }
+    };
+
+    Ok(NativeHandles { display, window })
+}
+
+/// Name of the raw window handle variant, for error reports
+pub fn handle_kind(handle: &RawWindowHandle) -> &'static str {
+    match handle {
+        RawWindowHandle::Xlib(_) => "Xlib",
+        RawWindowHandle::Xcb(_) => "Xcb",
+        RawWindowHandle::Wayland(_) => "Wayland",
+        RawWindowHandle::AppKit(_) => "AppKit",
+        RawWindowHandle::UiKit(_) => "UiKit",
+        RawWindowHandle::Win32(_) => "Win32",
+        RawWindowHandle::WinRt(_) => "WinRt",
+        RawWindowHandle::Web(_) => "Web",
+        RawWindowHandle::AndroidNdk(_) => "AndroidNdk",
+        RawWindowHandle::Orbital(_) => "Orbital",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raw_window_handle::{WaylandHandle, WebHandle, Win32Handle, XcbHandle, XlibHandle};
+
+    fn pointer(address: usize) -> *mut c_void {
+        address as *mut c_void
+    }
+
+    #[test]
+    fn xlib_maps_display_and_window() {
+        let mut handle = XlibHandle::empty();
+        handle.display = pointer(0x10);
+        handle.window = 0x20;
+
+        let handles = native_handles(RawWindowHandle::Xlib(handle)).unwrap();
+        assert_eq!(handles.display, pointer(0x10));
+        assert_eq!(handles.window, pointer(0x20));
+    }
+
+    #[test]
+    fn xcb_maps_connection_and_window() {
+        let mut handle = XcbHandle::empty();
+        handle.connection = pointer(0x30);
+        handle.window = 0x40;
+
+        let handles = native_handles(RawWindowHandle::Xcb(handle)).unwrap();
+        assert_eq!(handles.display, pointer(0x30));
+        assert_eq!(handles.window, pointer(0x40));
+    }
+
+    #[test]
+    fn wayland_renders_to_the_surface() {
+        let mut handle = WaylandHandle::empty();
+        handle.display = pointer(0x50);
+        handle.surface = pointer(0x60);
+
+        let pd = native_handles(RawWindowHandle::Wayland(handle))
+            .unwrap()
+            .platform_data();
+        assert_eq!(pd.ndt, pointer(0x50));
+        assert_eq!(pd.nwh, pointer(0x60));
+    }
+
+    #[test]
+    fn win32_has_no_display() {
+        let mut handle = Win32Handle::empty();
+        handle.hwnd = pointer(0x70);
+        handle.hinstance = pointer(0x80);
+
+        let handles = native_handles(RawWindowHandle::Win32(handle)).unwrap();
+        assert!(handles.display.is_null());
+        assert_eq!(handles.window, pointer(0x70));
+    }
+
+    #[test]
+    fn unsupported_handles_are_named() {
+        match native_handles(RawWindowHandle::Web(WebHandle::empty())) {
+            Err(InitializationError::UnsupportedWindowHandle(kind)) => assert_eq!(kind, "Web"),
+            other => panic!("expected an unsupported handle error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn resolution_math() {
+        let resolution = Resolution::new(1920, 1080);
+        assert_eq!(resolution.as_vec2(), Vec2::new(1920.0, 1080.0));
+        assert!((resolution.aspect_ratio() - 16.0 / 9.0).abs() < f32::EPSILON);
+        assert_eq!(Resolution::new(640, 0).aspect_ratio(), 640.0);
+    }
+
+    #[test]
+    fn resolution_fills_init() {
+        assert_eq!(Resolution::default(), Resolution::new(0, 0));
+
+        let mut init = Init::new();
+        Resolution::new(1280, 720).apply(&mut init);
+        assert_eq!(init.resolution.width, 1280);
+        assert_eq!(init.resolution.height, 720);
+    }
+}
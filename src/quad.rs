@@ -0,0 +1,138 @@
+use crate::renderer::{self, Renderer};
+use crate::view::ViewId;
+use bgfx_rs::static_lib::{
+    AddArgs, Attrib, AttribType, Program, RendererType, Texture as TextureHandle,
+    TransientIndexBuffer, TransientVertexBuffer, Uniform, UniformType, VertexLayoutBuilder,
+};
+use glam::{Vec2, Vec4};
+
+/// 16-bit indices address at most 65536 vertices per draw
+const MAX_QUADS_PER_BATCH: usize = (u16::MAX as usize + 1) / 4;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+struct QuadVertex {
+    x: f32,
+    y: f32,
+    z: f32,
+    u: f32,
+    v: f32,
+    abgr: u32,
+}
+
+/// Pack `(r, g, b, a)` in `0..=1` into the `0xAABBGGRR` layout of the color attribute
+pub(crate) fn pack_abgr(color: Vec4) -> u32 {
+    let color = (color.clamp(Vec4::ZERO, Vec4::ONE) * 255.0).round();
+    ((color.w as u32) << 24) | ((color.z as u32) << 16) | ((color.y as u32) << 8) | color.x as u32
+}
+
+/// Textured, tinted quads collected on the CPU and submitted through transient buffers.
+/// The `s_texColor` sampler is owned by the batch and released when it is dropped.
+pub(crate) struct QuadBatch {
+    layout: VertexLayoutBuilder,
+    sampler: Uniform,
+    vertices: Vec<QuadVertex>,
+}
+
+impl QuadBatch {
+    pub fn new(renderer: &Renderer) -> Self {
+        let layout = VertexLayoutBuilder::new();
+        layout
+            .begin(RendererType::Noop)
+            .add(Attrib::Position, 3, AttribType::Float, AddArgs::default())
+            .add(Attrib::TexCoord0, 2, AttribType::Float, AddArgs::default())
+            .add(
+                Attrib::Color0,
+                4,
+                AttribType::Uint8,
+                AddArgs {
+                    normalized: true,
+                    as_int: false,
+                },
+            )
+            .end();
+
+        Self {
+            layout,
+            sampler: renderer.create_uniform("s_texColor", UniformType::Sampler, 1),
+            vertices: Vec::new(),
+        }
+    }
+
+    /// Queue a quad, corners and texture coordinates in clockwise order from the top-left
+    pub fn push(&mut self, corners: [Vec2; 4], uvs: [Vec2; 4], abgr: u32) {
+        for (position, uv) in corners.into_iter().zip(uvs) {
+            self.vertices.push(QuadVertex {
+                x: position.x,
+                y: position.y,
+                z: 0.0,
+                u: uv.x,
+                v: uv.y,
+                abgr,
+            });
+        }
+    }
+
+    /// Submit every queued quad to `view` textured with `texture`, returning the number of
+    /// draw calls. The batch is empty afterwards.
+    pub fn submit(
+        &mut self,
+        renderer: &Renderer,
+        view: ViewId,
+        texture: &TextureHandle,
+        program: &Program,
+    ) -> usize {
+        let mut draw_calls = 0;
+        for vertices in self.vertices.chunks(MAX_QUADS_PER_BATCH * 4) {
+            if submit_chunk(renderer, &self.layout, vertices) {
+                renderer.set_texture(0, &self.sampler, texture);
+                renderer.set_state(renderer::alpha_blend_state());
+                renderer.submit(view, program);
+                draw_calls += 1;
+            }
+        }
+
+        self.vertices.clear();
+        draw_calls
+    }
+}
+
+/// Copy `vertices` and their indices into transient buffers and bind them, `false` if bgfx
+/// is out of transient memory this frame
+fn submit_chunk(
+    renderer: &Renderer,
+    layout: &VertexLayoutBuilder,
+    vertices: &[QuadVertex],
+) -> bool {
+    let quads = vertices.len() / 4;
+    let mut tvb = TransientVertexBuffer::new();
+    let mut tib = TransientIndexBuffer::new();
+
+    if !renderer.alloc_transient_buffers(
+        &mut tvb,
+        layout,
+        vertices.len() as u32,
+        &mut tib,
+        (quads * 6) as u32,
+    ) {
+        return false;
+    }
+
+    // SAFETY: bgfx allocated room for exactly `vertices.len()` vertices of this layout
+    // and `quads * 6` 16-bit indices, both valid until the next call to `frame`.
+    unsafe {
+        std::slice::from_raw_parts_mut(tvb.data as *mut QuadVertex, vertices.len())
+            .copy_from_slice(vertices);
+
+        let indices = std::slice::from_raw_parts_mut(tib.data as *mut u16, quads * 6);
+        for (quad, chunk) in indices.chunks_exact_mut(6).enumerate() {
+            let base = (quad * 4) as u16;
+            chunk.copy_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+    }
+
+    renderer.set_transient_vertex_buffer(0, &tvb, vertices.len() as u32);
+    renderer.set_transient_index_buffer(&tib, (quads * 6) as u32);
+
+    true
+}
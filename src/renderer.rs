@@ -0,0 +1,292 @@
+use crate::error::InitializationError;
+use crate::platform::Resolution;
+use bgfx_rs::static_lib::{
+    ClearFlags, DbgTextClearArgs, DebugFlags, Init, Memory, Program, RendererType, ResetArgs,
+    ResetFlags, SetViewClearArgs, Shader, StateBlendFlags, StateWriteFlags, SubmitArgs, Texture,
+    TextureFormat, TextureInfo, TransientIndexBuffer, TransientVertexBuffer, Uniform, UniformType,
+    VertexLayoutBuilder, ViewMode,
+};
+use glam::Mat4;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Equivalent of `BGFX_STATE_BLEND_FUNC`, using the same factors for color and alpha
+pub const fn blend_func(src: u64, dst: u64) -> u64 {
+    let func = src | (dst << 4);
+    func | (func << 8)
+}
+
+/// Color writes with straight alpha blending and no depth test, for 2D drawing
+pub fn alpha_blend_state() -> u64 {
+    StateWriteFlags::R.bits()
+        | StateWriteFlags::G.bits()
+        | StateWriteFlags::B.bits()
+        | StateWriteFlags::A.bits()
+        | blend_func(
+            StateBlendFlags::SRC_ALPHA.bits(),
+            StateBlendFlags::INV_SRC_ALPHA.bits(),
+        )
+}
+
+/// bgfx is a process wide singleton, only one `Renderer` may exist at a time
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+/// Owns the bgfx context, shutting it down when dropped.
+///
+/// Rendering goes through a `&Renderer` so nothing can be submitted before bgfx is
+/// initialized or after it has shut down.
+pub struct Renderer {
+    renderer_type: RendererType,
+    // bgfx calls must stay on the thread that initialized it
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Renderer {
+    /// Initialize bgfx with the first of `renderers` that succeeds, `window` names the
+    /// kind of window handle in `init` for error reports
+    pub(crate) fn init(
+        init: &mut Init,
+        renderers: &[RendererType],
+        window: &'static str,
+    ) -> Result<Self, InitializationError> {
+        if INITIALIZED.swap(true, Ordering::AcqRel) {
+            return Err(InitializationError::AlreadyInitialized);
+        }
+
+        for renderer in renderers.iter().copied() {
+            init.type_r = renderer;
+            if bgfx_rs::static_lib::init(init) {
+                return Ok(Self {
+                    renderer_type: bgfx_rs::static_lib::get_renderer_type(),
+                    _not_send: PhantomData,
+                });
+            }
+        }
+
+        INITIALIZED.store(false, Ordering::Release);
+        Err(InitializationError::Bgfx {
+            renderers: renderers.to_vec(),
+            width: init.resolution.width,
+            height: init.resolution.height,
+            window,
+        })
+    }
+
+    /// The backend bgfx picked, which may differ from the requested one
+    pub fn renderer_type(&self) -> RendererType {
+        self.renderer_type
+    }
+
+    /// Whether clip space depth is `-1..1` as in OpenGL rather than `0..1`
+    pub fn homogeneous_depth(&self) -> bool {
+        matches!(
+            self.renderer_type,
+            RendererType::OpenGL | RendererType::OpenGLES
+        )
+    }
+
+    /// Resize the backbuffer and apply new reset flags
+    pub fn reset(&self, resolution: Resolution, flags: ResetFlags) {
+        let args = ResetArgs {
+            flags: flags.bits(),
+            ..Default::default()
+        };
+        bgfx_rs::static_lib::reset(resolution.width, resolution.height, args);
+    }
+
+    /// Submit the current frame, returning the frame number
+    pub fn frame(&self) -> u32 {
+        bgfx_rs::static_lib::frame(false)
+    }
+
+    pub fn set_debug(&self, flags: DebugFlags) {
+        bgfx_rs::static_lib::set_debug(flags.bits());
+    }
+
+    /// Clear the buffers in `flags` before drawing to `view`, `rgba` is `0xRRGGBBAA`
+    pub fn set_view_clear(&self, view: u16, flags: ClearFlags, rgba: u32) {
+        bgfx_rs::static_lib::set_view_clear(
+            view,
+            flags.bits(),
+            SetViewClearArgs {
+                rgba,
+                ..Default::default()
+            },
+        );
+    }
+
+    pub fn set_view_rect(&self, view: u16, x: u16, y: u16, resolution: Resolution) {
+        bgfx_rs::static_lib::set_view_rect(
+            view,
+            x,
+            y,
+            resolution.width as u16,
+            resolution.height as u16,
+        );
+    }
+
+    pub fn set_view_transform(&self, view: u16, view_matrix: &Mat4, projection: &Mat4) {
+        bgfx_rs::static_lib::set_view_transform(
+            view,
+            &view_matrix.to_cols_array(),
+            &projection.to_cols_array(),
+        );
+    }
+
+    pub fn set_view_mode(&self, view: u16, mode: ViewMode) {
+        bgfx_rs::static_lib::set_view_mode(view, mode);
+    }
+
+    /// Submit views in `order` instead of by id, views left out follow in id order
+    pub fn set_view_order(&self, order: &[u16]) {
+        bgfx_rs::static_lib::set_view_order(0, order.len() as u16, order);
+    }
+
+    /// Make sure `view` is cleared even if nothing is submitted to it
+    pub fn touch(&self, view: u16) {
+        bgfx_rs::static_lib::touch(view);
+    }
+
+    pub fn dbg_text_clear(&self) {
+        bgfx_rs::static_lib::dbg_text_clear(DbgTextClearArgs::default());
+    }
+
+    /// Print `text` at a character cell, `attr` packs the background and foreground colors
+    pub fn dbg_text(&self, x: u16, y: u16, attr: u8, text: &str) {
+        bgfx_rs::static_lib::dbg_text(x, y, attr, text);
+    }
+
+    /// Create a shader from a compiled binary, bgfx keeps its own copy of `data`
+    pub fn create_shader(&self, data: &[u8]) -> Shader {
+        bgfx_rs::static_lib::create_shader(&Memory::copy(data))
+    }
+
+    /// Link a program. The `Shader` handles still own the shaders and destroy them when
+    /// dropped, bgfx keeps them alive for as long as the program needs them.
+    pub fn create_program(&self, vertex: &Shader, fragment: &Shader) -> Program {
+        bgfx_rs::static_lib::create_program(vertex, fragment, false)
+    }
+
+    pub fn create_compute_program(&self, compute: &Shader) -> Program {
+        bgfx_rs::static_lib::create_compute_program(compute, false)
+    }
+
+    /// Create a 2D RGBA8 texture, `data` holds every mip level when `has_mips` is set
+    pub fn create_texture_rgba8(
+        &self,
+        width: u16,
+        height: u16,
+        has_mips: bool,
+        flags: u64,
+        data: &[u8],
+    ) -> Texture {
+        bgfx_rs::static_lib::create_texture_2d(
+            width,
+            height,
+            has_mips,
+            1,
+            TextureFormat::RGBA8,
+            flags,
+            &Memory::copy(data),
+        )
+    }
+
+    /// Create a 2D RGBA8 texture without contents. bgfx only allows `update_texture_2d` on
+    /// textures created without initial data.
+    pub fn create_dynamic_texture_rgba8(&self, width: u16, height: u16, flags: u64) -> Texture {
+        bgfx_rs::static_lib::create_texture_2d(
+            width,
+            height,
+            false,
+            1,
+            TextureFormat::RGBA8,
+            flags,
+            &Memory::null(),
+        )
+    }
+
+    /// Replace a rectangle of the base level, `data` holds tightly packed RGBA8 rows
+    pub fn update_texture_2d(
+        &self,
+        texture: &Texture,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        data: &[u8],
+    ) {
+        // A pitch of u16::MAX tells bgfx the rows are tightly packed
+        bgfx_rs::static_lib::update_texture_2d(
+            texture,
+            0,
+            0,
+            x,
+            y,
+            width,
+            height,
+            &Memory::copy(data),
+            u16::MAX,
+        );
+    }
+
+    /// Create a texture from a DDS or KTX container
+    pub fn create_texture(&self, data: &[u8], flags: u64) -> Texture {
+        let mut info = TextureInfo::new();
+        bgfx_rs::static_lib::create_texture(&Memory::copy(data), flags, 0, &mut info)
+    }
+
+    pub fn create_uniform(&self, name: &str, kind: UniformType, count: u16) -> Uniform {
+        bgfx_rs::static_lib::create_uniform(name, kind, count)
+    }
+
+    /// Allocate transient buffers for one draw, `false` if bgfx ran out of space this frame
+    pub fn alloc_transient_buffers(
+        &self,
+        vertices: &mut TransientVertexBuffer,
+        layout: &VertexLayoutBuilder,
+        vertex_count: u32,
+        indices: &mut TransientIndexBuffer,
+        index_count: u32,
+    ) -> bool {
+        bgfx_rs::static_lib::alloc_transient_buffers(
+            vertices,
+            layout,
+            vertex_count,
+            indices,
+            index_count,
+            false,
+        )
+    }
+
+    pub fn set_transient_vertex_buffer(
+        &self,
+        stream: u8,
+        buffer: &TransientVertexBuffer,
+        count: u32,
+    ) {
+        bgfx_rs::static_lib::set_transient_vertex_buffer(stream, buffer, 0, count);
+    }
+
+    pub fn set_transient_index_buffer(&self, buffer: &TransientIndexBuffer, count: u32) {
+        bgfx_rs::static_lib::set_transient_index_buffer(buffer, 0, count);
+    }
+
+    pub fn set_texture(&self, stage: u8, sampler: &Uniform, texture: &Texture) {
+        bgfx_rs::static_lib::set_texture(stage, sampler, texture, u32::MAX);
+    }
+
+    pub fn set_state(&self, state: u64) {
+        bgfx_rs::static_lib::set_state(state, 0);
+    }
+
+    pub fn submit(&self, view: u16, program: &Program) {
+        bgfx_rs::static_lib::submit(view, program, SubmitArgs::default());
+    }
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        bgfx_rs::static_lib::shutdown();
+        INITIALIZED.store(false, Ordering::Release);
+    }
+}
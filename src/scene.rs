@@ -0,0 +1,190 @@
+use crate::application::Application;
+use crate::error::Result;
+use crate::event::Event;
+use std::collections::VecDeque;
+
+/// What the scene stack should do after a scene has updated
+pub enum Transition {
+    None,
+    /// Pause the current scene and enter a new one on top of it
+    Push(Box<dyn Scene>),
+    /// Leave the current scene and resume the one below it
+    Pop,
+    /// Leave the current scene and enter a new one in its place
+    Replace(Box<dyn Scene>),
+    /// Leave every scene and close the application
+    Quit,
+}
+
+/// A self-contained unit of the application such as a menu, level or pause screen
+pub trait Scene {
+    fn on_enter(&mut self, _app: &mut Application) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_exit(&mut self, _app: &mut Application) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called at the fixed update rate while this scene is on top of the stack
+    fn update(&mut self, app: &mut Application, dt: f64) -> Result<Transition>;
+
+    /// Called once per frame with the interpolation factor between updates
+    fn render(&mut self, app: &mut Application, alpha: f32) -> Result<()>;
+
+    /// Returns `true` when the event was consumed and shouldn't reach scenes below
+    fn handle_event(&mut self, _app: &mut Application, _event: &Event) -> bool {
+        false
+    }
+
+    /// Transparent scenes, such as a pause overlay, render on top of the scene below them
+    fn is_transparent(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Default)]
+pub struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+    /// Transitions requested while the scenes were taken out by `take`
+    pending: VecDeque<Transition>,
+    detached: bool,
+}
+
+impl SceneStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn push(&mut self, app: &mut Application, mut scene: Box<dyn Scene>) -> Result<()> {
+        scene.on_enter(app)?;
+        self.scenes.push(scene);
+        Ok(())
+    }
+
+    pub fn pop(&mut self, app: &mut Application) -> Result<Option<Box<dyn Scene>>> {
+        match self.scenes.pop() {
+            Some(mut scene) => {
+                scene.on_exit(app)?;
+                Ok(Some(scene))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn replace(&mut self, app: &mut Application, scene: Box<dyn Scene>) -> Result<()> {
+        self.pop(app)?;
+        self.push(app, scene)
+    }
+
+    /// Exit every scene, top first
+    pub fn clear(&mut self, app: &mut Application) -> Result<()> {
+        while self.pop(app)?.is_some() {}
+        Ok(())
+    }
+
+    pub fn apply(&mut self, app: &mut Application, transition: Transition) -> Result<()> {
+        match transition {
+            Transition::None => Ok(()),
+            Transition::Push(scene) => self.push(app, scene),
+            Transition::Pop => self.pop(app).map(|_| ()),
+            Transition::Replace(scene) => self.replace(app, scene),
+            Transition::Quit => {
+                app.set_should_close(true);
+                self.clear(app)
+            }
+        }
+    }
+
+    /// Update the top scene and apply the transition it returns
+    pub fn update(&mut self, app: &mut Application, dt: f64) -> Result<()> {
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.update(app, dt)?,
+            None => return Ok(()),
+        };
+
+        self.apply(app, transition)
+    }
+
+    /// Render the top scene and any scenes visible through transparent ones, bottom first
+    pub fn render(&mut self, app: &mut Application, alpha: f32) -> Result<()> {
+        let first = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.is_transparent())
+            .unwrap_or(0);
+
+        for scene in &mut self.scenes[first..] {
+            scene.render(app, alpha)?;
+        }
+
+        Ok(())
+    }
+
+    /// Take the scenes out so they can borrow the application, leaving an empty stack that
+    /// queues the transitions requested meanwhile
+    pub(crate) fn take(&mut self) -> SceneStack {
+        let empty = SceneStack {
+            detached: true,
+            ..Default::default()
+        };
+        std::mem::replace(self, empty)
+    }
+
+    /// Put back the scenes taken by `take`, keeping the transitions queued meanwhile
+    pub(crate) fn restore(&mut self, taken: SceneStack) {
+        let changes = std::mem::replace(self, taken);
+        self.pending.extend(changes.pending);
+    }
+
+    /// `true` while the scenes are taken out and transitions have to wait
+    pub(crate) fn is_detached(&self) -> bool {
+        self.detached
+    }
+
+    pub(crate) fn queue(&mut self, transition: Transition) {
+        self.pending.push_back(transition);
+    }
+
+    pub(crate) fn next_pending(&mut self) -> Option<Transition> {
+        self.pending.pop_front()
+    }
+
+    /// Offer `event` to each scene from the top down until one consumes it
+    pub fn handle_event(&mut self, app: &mut Application, event: &Event) -> bool {
+        self.scenes
+            .iter_mut()
+            .rev()
+            .any(|scene| scene.handle_event(app, event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transitions_queued_while_taken_survive_restore() {
+        let mut stack = SceneStack::new();
+        assert!(!stack.is_detached());
+
+        let taken = stack.take();
+        assert!(stack.is_detached());
+        stack.queue(Transition::Pop);
+        stack.queue(Transition::Quit);
+
+        stack.restore(taken);
+        assert!(!stack.is_detached());
+        assert!(matches!(stack.next_pending(), Some(Transition::Pop)));
+        assert!(matches!(stack.next_pending(), Some(Transition::Quit)));
+        assert!(stack.next_pending().is_none());
+    }
+}
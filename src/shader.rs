@@ -0,0 +1,164 @@
+use crate::error::ShaderError;
+use crate::renderer::Renderer;
+use bgfx_rs::static_lib::{Program, RendererType, Shader};
+use std::path::{Path, PathBuf};
+
+/// Root of the compiled shader tree, laid out as `shaders/<backend>/<name>.bin`
+pub const SHADER_DIR: &str = "shaders";
+
+/// Where binaries are looked up by default, the build output when the `compile-shaders`
+/// feature is enabled and `SHADER_DIR` otherwise
+pub fn default_shader_dir() -> &'static str {
+    option_env!("COMPILED_SHADER_DIR").unwrap_or(SHADER_DIR)
+}
+
+/// Pipeline stage a shader binary was compiled for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Compute,
+}
+
+impl ShaderStage {
+    /// First byte of the `VSH`/`FSH`/`CSH` magic bgfx writes at the start of every binary
+    fn magic(self) -> u8 {
+        match self {
+            ShaderStage::Vertex => b'V',
+            ShaderStage::Fragment => b'F',
+            ShaderStage::Compute => b'C',
+        }
+    }
+}
+
+/// Subdirectory holding binaries for `renderer`, matching the names bgfx's own tools use
+pub fn backend_dir(renderer: RendererType) -> Option<&'static str> {
+    match renderer {
+        RendererType::Direct3D11 | RendererType::Direct3D12 => Some("dx11"),
+        RendererType::Metal => Some("metal"),
+        RendererType::OpenGL => Some("glsl"),
+        RendererType::OpenGLES => Some("essl"),
+        RendererType::Vulkan => Some("spirv"),
+        _ => None,
+    }
+}
+
+/// Loads precompiled shaders for the backend the renderer was initialized with
+pub struct ShaderLoader<'a> {
+    renderer: &'a Renderer,
+    root: PathBuf,
+}
+
+impl<'a> ShaderLoader<'a> {
+    pub fn new(renderer: &'a Renderer) -> Self {
+        Self {
+            renderer,
+            root: PathBuf::from(default_shader_dir()),
+        }
+    }
+
+    /// Look for `<backend>/<name>.bin` under `root` instead of the default directory
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    /// Path of the binary for shader `name` on the active backend
+    pub fn path(&self, name: &str) -> Result<PathBuf, ShaderError> {
+        let renderer = self.renderer.renderer_type();
+        let dir = backend_dir(renderer).ok_or(ShaderError::UnsupportedBackend(renderer))?;
+        Ok(self.root.join(dir).join(name).with_extension("bin"))
+    }
+
+    pub fn load_shader(&self, name: &str, stage: ShaderStage) -> Result<Shader, ShaderError> {
+        let path = self.path(name)?;
+        let data = read_binary(&path)?;
+        validate(&path, &data, stage)?;
+
+        Ok(self.renderer.create_shader(&data))
+    }
+
+    /// Load and link the vertex shader `vertex` with the fragment shader `fragment`
+    pub fn load_program(&self, vertex: &str, fragment: &str) -> Result<Program, ShaderError> {
+        let vertex = self.load_shader(vertex, ShaderStage::Vertex)?;
+        let fragment = self.load_shader(fragment, ShaderStage::Fragment)?;
+
+        Ok(self.renderer.create_program(&vertex, &fragment))
+    }
+
+    pub fn load_compute_program(&self, name: &str) -> Result<Program, ShaderError> {
+        let compute = self.load_shader(name, ShaderStage::Compute)?;
+        Ok(self.renderer.create_compute_program(&compute))
+    }
+}
+
+fn read_binary(path: &Path) -> Result<Vec<u8>, ShaderError> {
+    std::fs::read(path).map_err(|source| match source.kind() {
+        std::io::ErrorKind::NotFound => ShaderError::Missing(path.to_path_buf()),
+        _ => ShaderError::Io {
+            path: path.to_path_buf(),
+            source,
+        },
+    })
+}
+
+/// Check the header so a stale or mismatched file is reported instead of crashing bgfx
+fn validate(path: &Path, data: &[u8], stage: ShaderStage) -> Result<(), ShaderError> {
+    match data {
+        [kind, b'S', b'H', _, ..] if *kind == stage.magic() => Ok(()),
+        [b'V' | b'F' | b'C', b'S', b'H', _, ..] => Err(ShaderError::StageMismatch {
+            path: path.to_path_buf(),
+            expected: stage,
+        }),
+        _ => Err(ShaderError::Invalid(path.to_path_buf())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERTEX: &[u8] = b"VSH\x0b\x12\x34\x56\x78\x00\x00";
+
+    fn check(data: &[u8], stage: ShaderStage) -> Result<(), ShaderError> {
+        validate(Path::new("test.bin"), data, stage)
+    }
+
+    #[test]
+    fn accepts_matching_headers() {
+        assert!(check(VERTEX, ShaderStage::Vertex).is_ok());
+        assert!(check(b"FSH\x0b", ShaderStage::Fragment).is_ok());
+        assert!(check(b"CSH\x03\x00", ShaderStage::Compute).is_ok());
+    }
+
+    #[test]
+    fn reports_the_wrong_stage() {
+        assert!(matches!(
+            check(VERTEX, ShaderStage::Fragment),
+            Err(ShaderError::StageMismatch {
+                expected: ShaderStage::Fragment,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_wrong_magic() {
+        for data in [&b"XSH\x0b\x00"[..], b"VSX\x0b\x00", b"#version 330\n"] {
+            assert!(matches!(
+                check(data, ShaderStage::Vertex),
+                Err(ShaderError::Invalid(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_headers() {
+        for length in 0..4 {
+            assert!(matches!(
+                check(&VERTEX[..length], ShaderStage::Vertex),
+                Err(ShaderError::Invalid(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use crate::quad::{self, QuadBatch};
+use crate::renderer::Renderer;
+use crate::texture::Texture;
+use crate::view::ViewId;
+use bgfx_rs::static_lib::{Program, ViewMode};
+use glam::{Affine2, Vec2, Vec4};
+use std::rc::Rc;
+
+/// A textured quad, positioned by its origin in world units
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprite {
+    pub position: Vec2,
+    pub size: Vec2,
+    /// Pivot for rotation and scaling, `(0, 0)` is the top-left corner and `(1, 1)` the
+    /// bottom-right
+    pub origin: Vec2,
+    /// Clockwise rotation in radians
+    pub rotation: f32,
+    pub scale: Vec2,
+    /// Texture coordinates of the top-left and bottom-right corners
+    pub uv: (Vec2, Vec2),
+    /// Multiplied with the texture color, `(r, g, b, a)` in `0..=1`
+    pub color: Vec4,
+    /// Sprites on a lower layer always draw first
+    pub layer: i32,
+    /// Order within a layer, lower depths draw first
+    pub depth: f32,
+}
+
+impl Sprite {
+    pub fn new(position: Vec2, size: Vec2) -> Self {
+        Self {
+            position,
+            size,
+            origin: Vec2::splat(0.5),
+            rotation: 0.0,
+            scale: Vec2::ONE,
+            uv: (Vec2::ZERO, Vec2::ONE),
+            color: Vec4::ONE,
+            layer: 0,
+            depth: 0.0,
+        }
+    }
+
+    pub fn with_origin(mut self, origin: Vec2) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vec2) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_uv(mut self, min: Vec2, max: Vec2) -> Self {
+        self.uv = (min, max);
+        self
+    }
+
+    /// Show the pixel rectangle at `position` with `size` of a `texture` atlas
+    pub fn with_region(self, texture: &Texture, position: Vec2, size: Vec2) -> Self {
+        let texture_size = Vec2::new(texture.width() as f32, texture.height() as f32);
+        self.with_uv(position / texture_size, (position + size) / texture_size)
+    }
+
+    /// Mirror horizontally by swapping the texture coordinates
+    pub fn flipped_x(mut self) -> Self {
+        std::mem::swap(&mut self.uv.0.x, &mut self.uv.1.x);
+        self
+    }
+
+    pub fn flipped_y(mut self) -> Self {
+        std::mem::swap(&mut self.uv.0.y, &mut self.uv.1.y);
+        self
+    }
+
+    pub fn with_color(mut self, color: Vec4) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn with_depth(mut self, depth: f32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Transform from the unit square to world space
+    pub fn transform(&self) -> Affine2 {
+        Affine2::from_scale_angle_translation(self.size * self.scale, self.rotation, self.position)
+            * Affine2::from_translation(-self.origin)
+    }
+}
+
+/// Order of queued sprites within a layer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortMode {
+    /// Group by texture first for the fewest draw calls, depth only orders sprites that
+    /// share a texture
+    Texture,
+    /// Strict depth order, only consecutive sprites with the same texture are batched
+    Depth,
+}
+
+/// Collects sprites during a frame and submits them in as few draw calls as possible
+pub struct SpriteBatch {
+    view: ViewId,
+    sort_mode: SortMode,
+    quads: QuadBatch,
+    textures: Vec<Rc<Texture>>,
+    sprites: Vec<(usize, Sprite)>,
+}
+
+impl SpriteBatch {
+    pub fn new(renderer: &Renderer, view: ViewId) -> Self {
+        // Submission order is decided by the batch, bgfx shouldn't reorder it
+        renderer.set_view_mode(view, ViewMode::Sequential);
+
+        Self {
+            view,
+            sort_mode: SortMode::Texture,
+            quads: QuadBatch::new(renderer),
+            textures: Vec::new(),
+            sprites: Vec::new(),
+        }
+    }
+
+    pub fn with_sort_mode(mut self, sort_mode: SortMode) -> Self {
+        self.sort_mode = sort_mode;
+        self
+    }
+
+    pub fn view(&self) -> ViewId {
+        self.view
+    }
+
+    /// Number of sprites queued since the last flush
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    /// Queue `sprite` to be drawn with `texture` on the next flush
+    pub fn draw(&mut self, texture: &Rc<Texture>, sprite: Sprite) {
+        let index = match self
+            .textures
+            .iter()
+            .position(|existing| Rc::ptr_eq(existing, texture))
+        {
+            Some(index) => index,
+            None => {
+                self.textures.push(Rc::clone(texture));
+                self.textures.len() - 1
+            }
+        };
+
+        self.sprites.push((index, sprite));
+    }
+
+    /// Sort and submit every queued sprite with `program`, returning the number of draw
+    /// calls. The batch is empty afterwards.
+    pub fn flush(&mut self, renderer: &Renderer, program: &Program) -> usize {
+        sort_sprites(&mut self.sprites, self.sort_mode);
+
+        let mut draw_calls = 0;
+        let mut start = 0;
+        while start < self.sprites.len() {
+            let texture = self.sprites[start].0;
+            let end = self.sprites[start..]
+                .iter()
+                .position(|(index, _)| *index != texture)
+                .map_or(self.sprites.len(), |offset| start + offset);
+
+            for (_, sprite) in &self.sprites[start..end] {
+                push_quad(&mut self.quads, sprite);
+            }
+            draw_calls += self.quads.submit(
+                renderer,
+                self.view,
+                self.textures[texture].handle(),
+                program,
+            );
+
+            start = end;
+        }
+
+        self.sprites.clear();
+        self.textures.clear();
+        draw_calls
+    }
+}
+
+/// Order `(texture index, sprite)` pairs by layer, then by `sort_mode`. Stable, so sprites
+/// that compare equal keep their submission order.
+fn sort_sprites(sprites: &mut [(usize, Sprite)], sort_mode: SortMode) {
+    sprites.sort_by(|(a_texture, a), (b_texture, b)| {
+        let depth = a.depth.total_cmp(&b.depth);
+        let texture = a_texture.cmp(b_texture);
+        a.layer.cmp(&b.layer).then(match sort_mode {
+            SortMode::Texture => texture.then(depth),
+            SortMode::Depth => depth.then(texture),
+        })
+    });
+}
+
+fn push_quad(quads: &mut QuadBatch, sprite: &Sprite) {
+    let transform = sprite.transform();
+    let (uv_min, uv_max) = sprite.uv;
+
+    // Corners in clockwise order starting top-left
+    let corners = [
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+    ];
+    quads.push(
+        corners.map(|corner| transform.transform_point2(corner)),
+        corners.map(|corner| uv_min + (uv_max - uv_min) * corner),
+        quad::pack_abgr(sprite.color),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sprites tagged by their x position, so the sorted order can be read back
+    fn queue(sprites: &[(usize, i32, f32)]) -> Vec<(usize, Sprite)> {
+        sprites
+            .iter()
+            .enumerate()
+            .map(|(tag, &(texture, layer, depth))| {
+                let sprite = Sprite::new(Vec2::new(tag as f32, 0.0), Vec2::ONE)
+                    .with_layer(layer)
+                    .with_depth(depth);
+                (texture, sprite)
+            })
+            .collect()
+    }
+
+    fn sorted(sprites: &[(usize, i32, f32)], sort_mode: SortMode) -> Vec<usize> {
+        let mut queued = queue(sprites);
+        sort_sprites(&mut queued, sort_mode);
+        queued
+            .iter()
+            .map(|(_, sprite)| sprite.position.x as usize)
+            .collect()
+    }
+
+    const SPRITES: &[(usize, i32, f32)] = &[
+        (1, 0, 0.5),
+        (0, 0, 2.0),
+        (1, 0, 1.0),
+        (0, -1, 9.0),
+        (0, 0, 0.5),
+    ];
+
+    #[test]
+    fn texture_mode_groups_by_texture_within_a_layer() {
+        assert_eq!(sorted(SPRITES, SortMode::Texture), [3, 4, 1, 0, 2]);
+    }
+
+    #[test]
+    fn depth_mode_orders_strictly_by_depth_within_a_layer() {
+        assert_eq!(sorted(SPRITES, SortMode::Depth), [3, 4, 0, 2, 1]);
+    }
+
+    #[test]
+    fn equal_keys_keep_submission_order() {
+        let sprites = [
+            (2, 1, 0.0),
+            (0, 0, 3.0),
+            (2, 1, 0.0),
+            (2, 1, 0.0),
+            (0, 0, 3.0),
+        ];
+        for sort_mode in [SortMode::Texture, SortMode::Depth] {
+            assert_eq!(sorted(&sprites, sort_mode), [1, 4, 0, 2, 3]);
+        }
+    }
+}
@@ -0,0 +1,484 @@
+use crate::error::TextureError;
+use crate::renderer::Renderer;
+use bgfx_rs::static_lib::{SamplerFlags, Texture as TextureHandle, TextureFlags};
+use image::imageops::FilterType;
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Filter {
+    Linear,
+    /// Point sampling, keeps pixel art crisp
+    Nearest,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Wrap {
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+/// How a texture is uploaded and sampled
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureOptions {
+    /// Generate a full mip chain for decoded images, containers keep the mips they ship with
+    pub mipmaps: bool,
+    /// Treat the texels as sRGB so sampling returns linear values
+    pub srgb: bool,
+    pub filter: Filter,
+    pub wrap: Wrap,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            mipmaps: true,
+            srgb: false,
+            filter: Filter::Linear,
+            wrap: Wrap::Repeat,
+        }
+    }
+}
+
+impl TextureOptions {
+    /// Unfiltered, clamped and without mips, for sprite sheets and tile atlases
+    pub fn pixel_art() -> Self {
+        Self {
+            mipmaps: false,
+            srgb: false,
+            filter: Filter::Nearest,
+            wrap: Wrap::Clamp,
+        }
+    }
+
+    pub fn with_mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    pub fn with_srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: Wrap) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Combined texture and sampler flags passed to bgfx
+    pub fn flags(&self) -> u64 {
+        let mut flags = match self.srgb {
+            true => TextureFlags::SRGB.bits(),
+            false => 0,
+        };
+
+        if self.filter == Filter::Nearest {
+            flags |= SamplerFlags::MIN_POINT.bits()
+                | SamplerFlags::MAG_POINT.bits()
+                | SamplerFlags::MIP_POINT.bits();
+        }
+        flags |= match self.wrap {
+            Wrap::Repeat => 0,
+            Wrap::Clamp => SamplerFlags::U_CLAMP.bits() | SamplerFlags::V_CLAMP.bits(),
+            Wrap::Mirror => SamplerFlags::U_MIRROR.bits() | SamplerFlags::V_MIRROR.bits(),
+        };
+
+        flags
+    }
+}
+
+/// A texture on the GPU, bgfx releases it when this is dropped
+pub struct Texture {
+    handle: TextureHandle,
+    width: u32,
+    height: u32,
+    mip_count: u32,
+    dynamic: bool,
+}
+
+impl Texture {
+    /// Decode `path` and upload it, the format is picked from the extension
+    pub fn load(
+        renderer: &Renderer,
+        path: impl AsRef<Path>,
+        options: TextureOptions,
+    ) -> Result<Self, TextureError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|source| TextureError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("dds" | "ktx") => Self::from_container(renderer, path, &data, options),
+            Some("png" | "jpg" | "jpeg" | "tga") => {
+                let image =
+                    image::load_from_memory(&data).map_err(|source| TextureError::Decode {
+                        path: path.to_path_buf(),
+                        source,
+                    })?;
+                Self::from_image(renderer, &image.to_rgba8(), options)
+            }
+            _ => Err(TextureError::Unsupported(path.to_path_buf())),
+        }
+    }
+
+    /// Upload decoded RGBA8 pixels, generating mips if requested
+    pub fn from_image(
+        renderer: &Renderer,
+        image: &RgbaImage,
+        options: TextureOptions,
+    ) -> Result<Self, TextureError> {
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 || width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return Err(TextureError::InvalidSize { width, height });
+        }
+
+        let mut data = image.as_raw().clone();
+        let mut mip_count = 1;
+        if options.mipmaps {
+            let mut level = image.clone();
+            while level.width() > 1 || level.height() > 1 {
+                let (width, height) = ((level.width() / 2).max(1), (level.height() / 2).max(1));
+                level = image::imageops::resize(&level, width, height, FilterType::Triangle);
+                data.extend_from_slice(level.as_raw());
+                mip_count += 1;
+            }
+        }
+
+        let handle = renderer.create_texture_rgba8(
+            width as u16,
+            height as u16,
+            mip_count > 1,
+            options.flags(),
+            &data,
+        );
+
+        Ok(Self {
+            handle,
+            width,
+            height,
+            mip_count,
+            dynamic: false,
+        })
+    }
+
+    /// Allocate an empty RGBA8 texture without mips that can be changed with
+    /// `Texture::update`, such as a glyph atlas
+    pub fn dynamic(
+        renderer: &Renderer,
+        width: u32,
+        height: u32,
+        options: TextureOptions,
+    ) -> Result<Self, TextureError> {
+        if width == 0 || height == 0 || width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return Err(TextureError::InvalidSize { width, height });
+        }
+
+        Ok(Self {
+            handle: renderer.create_dynamic_texture_rgba8(
+                width as u16,
+                height as u16,
+                options.flags(),
+            ),
+            width,
+            height,
+            mip_count: 1,
+            dynamic: true,
+        })
+    }
+
+    /// Copy `image` into the texture with its top-left corner at `(x, y)`
+    pub fn update(
+        &self,
+        renderer: &Renderer,
+        x: u32,
+        y: u32,
+        image: &RgbaImage,
+    ) -> Result<(), TextureError> {
+        if !self.dynamic {
+            return Err(TextureError::Immutable);
+        }
+
+        let (width, height) = image.dimensions();
+        let fits = |start: u32, length: u32, limit: u32| {
+            start.checked_add(length).map_or(false, |end| end <= limit)
+        };
+        if !fits(x, width, self.width) || !fits(y, height, self.height) {
+            return Err(TextureError::UpdateOutOfBounds {
+                x,
+                y,
+                width,
+                height,
+            });
+        }
+
+        renderer.update_texture_2d(
+            &self.handle,
+            x as u16,
+            y as u16,
+            width as u16,
+            height as u16,
+            image.as_raw(),
+        );
+        Ok(())
+    }
+
+    /// Upload a DDS or KTX container as is, bgfx decodes compressed formats and mips
+    fn from_container(
+        renderer: &Renderer,
+        path: &Path,
+        data: &[u8],
+        options: TextureOptions,
+    ) -> Result<Self, TextureError> {
+        let (width, height, mip_count) =
+            container_info(data).map_err(|reason| TextureError::Container {
+                path: path.to_path_buf(),
+                reason,
+            })?;
+
+        Ok(Self {
+            handle: renderer.create_texture(data, options.flags()),
+            width,
+            height,
+            mip_count,
+            dynamic: false,
+        })
+    }
+
+    pub fn handle(&self) -> &TextureHandle {
+        &self.handle
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn mip_count(&self) -> u32 {
+        self.mip_count
+    }
+}
+
+/// Read the size and mip count from a DDS or KTX header
+fn container_info(data: &[u8]) -> Result<(u32, u32, u32), &'static str> {
+    const KTX: &[u8] = b"\xABKTX 11\xBB\r\n\x1A\n";
+
+    let read = |offset: usize| -> Result<u32, &'static str> {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .ok_or("truncated header")
+    };
+
+    let (width, height, mips) = if data.starts_with(b"DDS ") {
+        (read(16)?, read(12)?, read(28)?)
+    } else if data.starts_with(KTX) {
+        if read(12)? != 0x0403_0201 {
+            return Err("big endian KTX files are not supported");
+        }
+        (read(36)?, read(40)?, read(56)?)
+    } else {
+        return Err("unrecognized container header");
+    };
+
+    if width == 0 || height == 0 {
+        return Err("zero sized texture");
+    }
+    // Both formats store 0 when only the base level is present
+    Ok((width, height, mips.max(1)))
+}
+
+/// Shares loaded textures so each file is uploaded once per set of options. Loading the
+/// same path with different options, such as pixel art and filtered, gives two textures.
+#[derive(Default)]
+pub struct TextureCache {
+    textures: HashMap<(PathBuf, TextureOptions), Rc<Texture>>,
+}
+
+impl TextureCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the cached texture for `path` and `options`, loading it on first use
+    pub fn load(
+        &mut self,
+        renderer: &Renderer,
+        path: impl AsRef<Path>,
+        options: TextureOptions,
+    ) -> Result<Rc<Texture>, TextureError> {
+        let key = (path.as_ref().to_path_buf(), options);
+        if let Some(texture) = self.textures.get(&key) {
+            return Ok(Rc::clone(texture));
+        }
+
+        let texture = Rc::new(Texture::load(renderer, &key.0, options)?);
+        self.textures.insert(key, Rc::clone(&texture));
+        Ok(texture)
+    }
+
+    pub fn get(&self, path: impl AsRef<Path>, options: TextureOptions) -> Option<Rc<Texture>> {
+        self.textures
+            .get(&(path.as_ref().to_path_buf(), options))
+            .cloned()
+    }
+
+    /// Forget `path` loaded with `options`, the texture is destroyed once nothing else
+    /// references it
+    pub fn remove(
+        &mut self,
+        path: impl AsRef<Path>,
+        options: TextureOptions,
+    ) -> Option<Rc<Texture>> {
+        self.textures
+            .remove(&(path.as_ref().to_path_buf(), options))
+    }
+
+    /// Forget every variant of `path`, returning how many were cached
+    pub fn remove_all(&mut self, path: impl AsRef<Path>) -> usize {
+        let before = self.textures.len();
+        self.textures
+            .retain(|(cached, _), _| cached.as_path() != path.as_ref());
+        before - self.textures.len()
+    }
+
+    /// Drop cached textures that are no longer used anywhere else
+    pub fn collect_unused(&mut self) {
+        self.textures
+            .retain(|_, texture| Rc::strong_count(texture) > 1);
+    }
+
+    pub fn clear(&mut self) {
+        self.textures.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KTX: &[u8] = b"\xABKTX 11\xBB\r\n\x1A\n";
+
+    /// A header of `len` bytes starting with `magic`, with `fields` written at their offsets
+    fn header(magic: &[u8], len: usize, fields: &[(usize, u32)]) -> Vec<u8> {
+        let mut data = vec![0; len];
+        data[..magic.len()].copy_from_slice(magic);
+        for &(offset, value) in fields {
+            data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        data
+    }
+
+    fn dds(width: u32, height: u32, mips: u32) -> Vec<u8> {
+        header(
+            b"DDS ",
+            128,
+            &[(4, 124), (12, height), (16, width), (28, mips)],
+        )
+    }
+
+    fn ktx(width: u32, height: u32, mips: u32) -> Vec<u8> {
+        header(
+            KTX,
+            64,
+            &[(12, 0x0403_0201), (36, width), (40, height), (56, mips)],
+        )
+    }
+
+    #[test]
+    fn default_flags_are_filtered_and_repeating() {
+        assert_eq!(TextureOptions::default().flags(), 0);
+    }
+
+    #[test]
+    fn options_map_to_bgfx_flags() {
+        let point = SamplerFlags::MIN_POINT.bits()
+            | SamplerFlags::MAG_POINT.bits()
+            | SamplerFlags::MIP_POINT.bits();
+        let clamp = SamplerFlags::U_CLAMP.bits() | SamplerFlags::V_CLAMP.bits();
+        let mirror = SamplerFlags::U_MIRROR.bits() | SamplerFlags::V_MIRROR.bits();
+
+        assert_eq!(TextureOptions::pixel_art().flags(), point | clamp);
+        assert_eq!(
+            TextureOptions::default().with_wrap(Wrap::Mirror).flags(),
+            mirror
+        );
+        assert_eq!(
+            TextureOptions::default()
+                .with_srgb(true)
+                .with_filter(Filter::Nearest)
+                .flags(),
+            TextureFlags::SRGB.bits() | point
+        );
+        // Mips are generated on upload and don't affect the flags
+        assert_eq!(TextureOptions::default().with_mipmaps(false).flags(), 0);
+    }
+
+    #[test]
+    fn reads_dds_and_ktx_headers() {
+        assert_eq!(container_info(&dds(256, 128, 9)), Ok((256, 128, 9)));
+        assert_eq!(container_info(&ktx(64, 32, 7)), Ok((64, 32, 7)));
+        // A mip count of 0 means only the base level
+        assert_eq!(container_info(&dds(4, 4, 0)), Ok((4, 4, 1)));
+        assert_eq!(container_info(&ktx(4, 4, 0)), Ok((4, 4, 1)));
+    }
+
+    #[test]
+    fn truncated_headers_are_errors() {
+        let dds = dds(16, 16, 1);
+        for len in [0, 3, 15, 20, 31] {
+            assert!(container_info(&dds[..len]).is_err(), "dds, {} bytes", len);
+        }
+        let ktx = ktx(16, 16, 1);
+        for len in [0, 11, 15, 39, 43, 59] {
+            assert!(container_info(&ktx[..len]).is_err(), "ktx, {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn bad_magic_is_an_error() {
+        let mut data = dds(16, 16, 1);
+        data[3] = b'X';
+        assert_eq!(container_info(&data), Err("unrecognized container header"));
+
+        let mut data = ktx(16, 16, 1);
+        data[5] = b'2';
+        assert_eq!(container_info(&data), Err("unrecognized container header"));
+        assert!(container_info(b"\x89PNG\r\n\x1a\n").is_err());
+    }
+
+    #[test]
+    fn unsupported_headers_are_errors() {
+        let mut data = ktx(16, 16, 1);
+        data[12..16].copy_from_slice(&0x0102_0304u32.to_le_bytes());
+        assert_eq!(
+            container_info(&data),
+            Err("big endian KTX files are not supported")
+        );
+        assert_eq!(container_info(&dds(0, 16, 1)), Err("zero sized texture"));
+    }
+}
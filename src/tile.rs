@@ -0,0 +1,649 @@
+use glam::{IVec2, UVec2, Vec2};
+use std::collections::HashMap;
+use std::ops::{BitOr, BitOrAssign};
+use std::path::PathBuf;
+
+mod render;
+mod tiled;
+
+pub use render::TileRenderer;
+
+/// Global tile identifier, `0` is reserved for empty cells
+pub type TileId = u32;
+
+/// Per-tile state bits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TileFlags(u8);
+
+impl TileFlags {
+    pub const NONE: Self = Self(0);
+    pub const FLIP_X: Self = Self(1 << 0);
+    pub const FLIP_Y: Self = Self(1 << 1);
+    /// Swap x and y before flipping, used together with the flips to rotate
+    pub const FLIP_DIAGONAL: Self = Self(1 << 2);
+    pub const SOLID: Self = Self(1 << 3);
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+}
+
+impl BitOr for TileFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for TileFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.insert(rhs);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Tile {
+    pub id: TileId,
+    pub flags: TileFlags,
+}
+
+impl Tile {
+    pub const EMPTY: Self = Self {
+        id: 0,
+        flags: TileFlags::NONE,
+    };
+
+    pub const fn new(id: TileId) -> Self {
+        Self {
+            id,
+            flags: TileFlags::NONE,
+        }
+    }
+
+    pub const fn with_flags(id: TileId, flags: TileFlags) -> Self {
+        Self { id, flags }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.id == 0
+    }
+}
+
+/// Describes how tile ids map onto a texture atlas
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tileset {
+    pub name: String,
+    /// Global id of the first tile in this set
+    pub first_id: TileId,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Number of tiles per atlas row
+    pub columns: u32,
+    pub tile_count: u32,
+    /// Pixels between neighbouring tiles in the atlas
+    pub spacing: u32,
+    /// Pixels around the edge of the atlas
+    pub margin: u32,
+    /// Atlas image, relative paths are resolved against the file the set was loaded from
+    pub image: Option<PathBuf>,
+    pub properties: Properties,
+}
+
+impl Tileset {
+    pub fn new(
+        name: impl Into<String>,
+        first_id: TileId,
+        tile_width: u32,
+        tile_height: u32,
+        columns: u32,
+        tile_count: u32,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            first_id,
+            tile_width,
+            tile_height,
+            columns,
+            tile_count,
+            spacing: 0,
+            margin: 0,
+            image: None,
+            properties: Properties::new(),
+        }
+    }
+
+    pub fn contains(&self, id: TileId) -> bool {
+        id >= self.first_id && id - self.first_id < self.tile_count
+    }
+
+    /// Column and row of `id` within the atlas
+    pub fn atlas_cell(&self, id: TileId) -> Option<UVec2> {
+        if !self.contains(id) || self.columns == 0 {
+            return None;
+        }
+
+        let local = id - self.first_id;
+        Some(UVec2::new(local % self.columns, local / self.columns))
+    }
+
+    /// Top-left pixel of `id` within the atlas
+    pub fn atlas_position(&self, id: TileId) -> Option<UVec2> {
+        self.atlas_cell(id).map(|cell| {
+            UVec2::new(
+                self.margin + cell.x * (self.tile_width + self.spacing),
+                self.margin + cell.y * (self.tile_height + self.spacing),
+            )
+        })
+    }
+
+    /// Pixel dimensions of an atlas holding every tile in this set
+    pub fn atlas_size(&self) -> UVec2 {
+        let rows = match self.columns {
+            0 => 0,
+            columns => (self.tile_count + columns - 1) / columns,
+        };
+        let extent = |count: u32, size: u32| match count {
+            0 => 0,
+            count => 2 * self.margin + count * size + (count - 1) * self.spacing,
+        };
+
+        UVec2::new(
+            extent(self.columns, self.tile_width),
+            extent(rows, self.tile_height),
+        )
+    }
+}
+
+/// A custom property attached to a map, layer, tileset or object
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    /// Packed as `0xAARRGGBB`
+    Color(u32),
+    File(PathBuf),
+    /// Id of another object on the map
+    Object(u32),
+}
+
+pub type Properties = HashMap<String, PropertyValue>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    /// Closed outline, points are relative to the object position
+    Polygon(Vec<Vec2>),
+    /// Open outline, points are relative to the object position
+    Polyline(Vec<Vec2>),
+}
+
+/// A free-form object placed in world units, such as a spawn point or trigger
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    pub kind: String,
+    pub position: Vec2,
+    pub size: Vec2,
+    /// Clockwise rotation in degrees
+    pub rotation: f32,
+    pub visible: bool,
+    pub shape: ObjectShape,
+    /// Set for objects that display a tile
+    pub tile: Option<Tile>,
+    pub properties: Properties,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    pub objects: Vec<MapObject>,
+    pub properties: Properties,
+}
+
+impl ObjectLayer {
+    pub fn object_by_name(&self, name: &str) -> Option<&MapObject> {
+        self.objects.iter().find(|object| object.name == name)
+    }
+}
+
+/// A half-open rectangle of tile coordinates, `max` is exclusive
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TileRect {
+    pub min: UVec2,
+    pub max: UVec2,
+}
+
+impl TileRect {
+    pub fn new(min: UVec2, max: UVec2) -> Self {
+        Self {
+            min,
+            max: max.max(min),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> u32 {
+        self.max.y - self.min.y
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width() == 0 || self.height() == 0
+    }
+
+    pub fn contains(&self, position: UVec2) -> bool {
+        position.cmpge(self.min).all() && position.cmplt(self.max).all()
+    }
+
+    /// Row-major iterator over every position in the rectangle
+    pub fn positions(&self) -> impl Iterator<Item = UVec2> {
+        let TileRect { min, max } = *self;
+        (min.y..max.y).flat_map(move |y| (min.x..max.x).map(move |x| UVec2::new(x, y)))
+    }
+}
+
+/// Number of cells in a `width` by `height` grid, `None` if it doesn't fit in memory
+pub(crate) fn cell_count(width: u32, height: u32) -> Option<usize> {
+    let count = (width as usize).checked_mul(height as usize)?;
+    let bytes = count.checked_mul(std::mem::size_of::<Tile>())?;
+    (bytes <= isize::MAX as usize).then(|| count)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileLayer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    pub properties: Properties,
+    width: u32,
+    height: u32,
+    tiles: Vec<Tile>,
+}
+
+impl TileLayer {
+    /// An empty layer, `None` if `width` by `height` cells don't fit in memory
+    pub fn new(name: impl Into<String>, width: u32, height: u32) -> Option<Self> {
+        let count = cell_count(width, height)?;
+        Self::from_tiles(name, width, height, vec![Tile::EMPTY; count])
+    }
+
+    /// Build a layer from row-major tiles, `None` if the length doesn't match
+    pub fn from_tiles(
+        name: impl Into<String>,
+        width: u32,
+        height: u32,
+        tiles: Vec<Tile>,
+    ) -> Option<Self> {
+        if cell_count(width, height) != Some(tiles.len()) {
+            return None;
+        }
+
+        Some(Self {
+            name: name.into(),
+            visible: true,
+            opacity: 1.0,
+            properties: Properties::new(),
+            width,
+            height,
+            tiles,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    fn index(&self, position: UVec2) -> Option<usize> {
+        if position.x < self.width && position.y < self.height {
+            Some(position.y as usize * self.width as usize + position.x as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, position: UVec2) -> Option<Tile> {
+        self.index(position).map(|index| self.tiles[index])
+    }
+
+    pub fn get_mut(&mut self, position: UVec2) -> Option<&mut Tile> {
+        self.index(position)
+            .map(move |index| &mut self.tiles[index])
+    }
+
+    /// Replace the tile at `position`, returning the previous one
+    pub fn set(&mut self, position: UVec2, tile: Tile) -> Option<Tile> {
+        self.get_mut(position)
+            .map(|slot| std::mem::replace(slot, tile))
+    }
+
+    pub fn fill(&mut self, rect: TileRect, tile: Tile) {
+        let rect = self.clamp(rect);
+        for position in rect.positions() {
+            self.set(position, tile);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.tiles.iter_mut().for_each(|tile| *tile = Tile::EMPTY);
+    }
+
+    /// Restrict `rect` to the layer bounds
+    pub fn clamp(&self, rect: TileRect) -> TileRect {
+        let bounds = UVec2::new(self.width, self.height);
+        TileRect::new(rect.min.min(bounds), rect.max.min(bounds))
+    }
+
+    /// Non-empty tiles inside `rect`, paired with their position
+    pub fn region(&self, rect: TileRect) -> impl Iterator<Item = (UVec2, Tile)> + '_ {
+        self.clamp(rect).positions().filter_map(move |position| {
+            self.get(position)
+                .filter(|tile| !tile.is_empty())
+                .map(|tile| (position, tile))
+        })
+    }
+}
+
+/// A grid of layered tiles sharing a common cell size
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileMap {
+    width: u32,
+    height: u32,
+    tile_width: u32,
+    tile_height: u32,
+    pub layers: Vec<TileLayer>,
+    pub object_layers: Vec<ObjectLayer>,
+    pub tilesets: Vec<Tileset>,
+    pub properties: Properties,
+}
+
+impl TileMap {
+    pub fn new(width: u32, height: u32, tile_width: u32, tile_height: u32) -> Self {
+        Self {
+            width,
+            height,
+            tile_width,
+            tile_height,
+            layers: Vec::new(),
+            object_layers: Vec::new(),
+            tilesets: Vec::new(),
+            properties: Properties::new(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn tile_size(&self) -> Vec2 {
+        Vec2::new(self.tile_width as f32, self.tile_height as f32)
+    }
+
+    /// Size of the whole map in world units
+    pub fn world_size(&self) -> Vec2 {
+        Vec2::new(self.width as f32, self.height as f32) * self.tile_size()
+    }
+
+    pub fn bounds(&self) -> TileRect {
+        TileRect::new(UVec2::ZERO, UVec2::new(self.width, self.height))
+    }
+
+    /// Append an empty layer sized to the map, returning its index, `None` if the map is too
+    /// large to allocate
+    pub fn add_layer(&mut self, name: impl Into<String>) -> Option<usize> {
+        self.layers
+            .push(TileLayer::new(name, self.width, self.height)?);
+        Some(self.layers.len() - 1)
+    }
+
+    pub fn layer(&self, index: usize) -> Option<&TileLayer> {
+        self.layers.get(index)
+    }
+
+    pub fn layer_mut(&mut self, index: usize) -> Option<&mut TileLayer> {
+        self.layers.get_mut(index)
+    }
+
+    pub fn layer_by_name(&self, name: &str) -> Option<&TileLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    pub fn object_layer_by_name(&self, name: &str) -> Option<&ObjectLayer> {
+        self.object_layers.iter().find(|layer| layer.name == name)
+    }
+
+    pub fn add_tileset(&mut self, tileset: Tileset) {
+        self.tilesets.push(tileset);
+    }
+
+    /// The tileset owning `id`
+    pub fn tileset_for(&self, id: TileId) -> Option<&Tileset> {
+        self.tilesets.iter().find(|tileset| tileset.contains(id))
+    }
+
+    pub fn in_bounds(&self, position: IVec2) -> bool {
+        position.x >= 0
+            && position.y >= 0
+            && (position.x as u32) < self.width
+            && (position.y as u32) < self.height
+    }
+
+    /// Tile containing `world`, may lie outside the map
+    pub fn world_to_tile(&self, world: Vec2) -> IVec2 {
+        (world / self.tile_size()).floor().as_ivec2()
+    }
+
+    /// Top-left corner of `tile` in world units
+    pub fn tile_to_world(&self, tile: IVec2) -> Vec2 {
+        tile.as_vec2() * self.tile_size()
+    }
+
+    pub fn tile_center(&self, tile: IVec2) -> Vec2 {
+        self.tile_to_world(tile) + self.tile_size() * 0.5
+    }
+
+    /// Tiles overlapping the world-space rectangle `min..max`, clamped to the map
+    pub fn world_region(&self, min: Vec2, max: Vec2) -> TileRect {
+        let (min, max) = (min.min(max), min.max(max));
+        let min = self.world_to_tile(min);
+        let max = (max / self.tile_size()).ceil().as_ivec2();
+        let bounds = IVec2::new(self.width as i32, self.height as i32);
+
+        TileRect::new(
+            min.clamp(IVec2::ZERO, bounds).as_uvec2(),
+            max.clamp(IVec2::ZERO, bounds).as_uvec2(),
+        )
+    }
+
+    /// Non-empty tiles of `layer` overlapping the world-space rectangle `min..max`
+    pub fn query(
+        &self,
+        layer: usize,
+        min: Vec2,
+        max: Vec2,
+    ) -> impl Iterator<Item = (UVec2, Tile)> + '_ {
+        let rect = self.world_region(min, max);
+        self.layers
+            .get(layer)
+            .into_iter()
+            .flat_map(move |layer| layer.region(rect))
+    }
+
+    /// Whether any layer has a solid tile at `position`
+    pub fn is_solid(&self, position: IVec2) -> bool {
+        if !self.in_bounds(position) {
+            return false;
+        }
+
+        let position = position.as_uvec2();
+        self.layers.iter().any(|layer| {
+            layer
+                .get(position)
+                .map_or(false, |tile| tile.flags.contains(TileFlags::SOLID))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid_map() -> TileMap {
+        let mut map = TileMap::new(4, 3, 16, 8);
+        let ground = map.add_layer("ground").unwrap();
+        map.layer_mut(ground)
+            .unwrap()
+            .set(UVec2::new(1, 2), Tile::with_flags(5, TileFlags::SOLID));
+        let decor = map.add_layer("decor").unwrap();
+        map.layer_mut(decor)
+            .unwrap()
+            .set(UVec2::new(2, 0), Tile::new(7));
+        map
+    }
+
+    #[test]
+    fn flags_combine_and_remove() {
+        let mut flags = TileFlags::FLIP_X | TileFlags::SOLID;
+        assert!(flags.contains(TileFlags::FLIP_X));
+        assert!(flags.contains(TileFlags::SOLID));
+        assert!(!flags.contains(TileFlags::FLIP_Y));
+        assert!(flags.contains(TileFlags::NONE));
+
+        flags |= TileFlags::FLIP_DIAGONAL;
+        flags.remove(TileFlags::FLIP_X);
+        assert_eq!(flags, TileFlags::FLIP_DIAGONAL | TileFlags::SOLID);
+        assert_eq!(TileFlags::from_bits(flags.bits()), flags);
+    }
+
+    #[test]
+    fn atlas_positions_account_for_margin_and_spacing() {
+        let mut tileset = Tileset::new("terrain", 10, 16, 8, 4, 10);
+        tileset.margin = 2;
+        tileset.spacing = 1;
+
+        assert_eq!(tileset.atlas_cell(9), None);
+        assert_eq!(tileset.atlas_cell(20), None);
+        assert_eq!(tileset.atlas_position(10), Some(UVec2::new(2, 2)));
+        assert_eq!(tileset.atlas_position(13), Some(UVec2::new(2 + 3 * 17, 2)));
+        assert_eq!(tileset.atlas_position(15), Some(UVec2::new(2 + 17, 2 + 9)));
+        // 4 columns and 3 rows, with spacing only between tiles
+        assert_eq!(
+            tileset.atlas_size(),
+            UVec2::new(4 + 4 * 16 + 3, 4 + 3 * 8 + 2)
+        );
+    }
+
+    #[test]
+    fn empty_tileset_has_no_atlas() {
+        let tileset = Tileset::new("empty", 1, 16, 16, 0, 0);
+        assert_eq!(tileset.atlas_position(1), None);
+        assert_eq!(tileset.atlas_size(), UVec2::ZERO);
+    }
+
+    #[test]
+    fn tile_rect_is_half_open() {
+        let rect = TileRect::new(UVec2::new(1, 1), UVec2::new(3, 2));
+        assert_eq!((rect.width(), rect.height()), (2, 1));
+        assert!(rect.contains(UVec2::new(2, 1)));
+        assert!(!rect.contains(UVec2::new(3, 1)));
+        assert_eq!(
+            rect.positions().collect::<Vec<_>>(),
+            [UVec2::new(1, 1), UVec2::new(2, 1)]
+        );
+
+        let inverted = TileRect::new(UVec2::new(3, 3), UVec2::new(1, 1));
+        assert!(inverted.is_empty());
+        assert_eq!(inverted.positions().count(), 0);
+    }
+
+    #[test]
+    fn layer_rejects_mismatched_tiles() {
+        assert!(TileLayer::from_tiles("layer", 2, 2, vec![Tile::EMPTY; 3]).is_none());
+        assert!(TileLayer::from_tiles("layer", 2, 2, vec![Tile::EMPTY; 4]).is_some());
+    }
+
+    #[test]
+    fn oversized_layers_are_rejected() {
+        assert!(TileLayer::new("layer", u32::MAX, u32::MAX).is_none());
+        assert!(TileMap::new(u32::MAX, u32::MAX, 16, 16)
+            .add_layer("layer")
+            .is_none());
+        assert_eq!(TileLayer::new("layer", 3, 2).unwrap().tiles().len(), 6);
+    }
+
+    #[test]
+    fn world_to_tile_floors_negative_positions() {
+        let map = solid_map();
+        assert_eq!(map.world_to_tile(Vec2::new(17.0, 7.9)), IVec2::new(1, 0));
+        assert_eq!(map.world_to_tile(Vec2::new(-0.5, -8.0)), IVec2::new(-1, -1));
+        assert_eq!(map.tile_to_world(IVec2::new(2, 1)), Vec2::new(32.0, 8.0));
+        assert_eq!(map.tile_center(IVec2::new(0, 0)), Vec2::new(8.0, 4.0));
+    }
+
+    #[test]
+    fn world_region_is_clamped_to_the_map() {
+        let map = solid_map();
+        assert_eq!(
+            map.world_region(Vec2::new(20.0, 4.0), Vec2::new(40.0, 12.0)),
+            TileRect::new(UVec2::new(1, 0), UVec2::new(3, 2))
+        );
+        assert_eq!(
+            map.world_region(Vec2::new(100.0, 100.0), Vec2::new(-100.0, -100.0)),
+            map.bounds()
+        );
+    }
+
+    #[test]
+    fn query_returns_non_empty_tiles() {
+        let map = solid_map();
+        let tiles: Vec<_> = map.query(1, Vec2::ZERO, map.world_size()).collect();
+        assert_eq!(tiles, [(UVec2::new(2, 0), Tile::new(7))]);
+        assert_eq!(map.query(2, Vec2::ZERO, map.world_size()).count(), 0);
+    }
+
+    #[test]
+    fn solid_checks_every_layer_and_bounds() {
+        let map = solid_map();
+        assert!(map.is_solid(IVec2::new(1, 2)));
+        assert!(!map.is_solid(IVec2::new(2, 0)));
+        assert!(!map.is_solid(IVec2::new(-1, 2)));
+        assert!(!map.is_solid(IVec2::new(4, 0)));
+    }
+}
@@ -0,0 +1,123 @@
+use super::{TileFlags, TileMap, Tileset};
+use crate::quad::QuadBatch;
+use crate::renderer::Renderer;
+use crate::texture::Texture;
+use crate::view::ViewId;
+use bgfx_rs::static_lib::{Program, ViewMode};
+use glam::{Mat4, Vec2};
+
+/// Draws the visible part of a `TileMap` as one batched quad mesh per layer and tileset
+pub struct TileRenderer {
+    view: ViewId,
+    quads: QuadBatch,
+}
+
+impl TileRenderer {
+    /// Draw to `view`, such as the id `RenderGraph::add` returned for a world pass
+    pub fn new(renderer: &Renderer, view: ViewId) -> Self {
+        renderer.set_view_mode(view, ViewMode::Sequential);
+
+        Self {
+            view,
+            quads: QuadBatch::new(renderer),
+        }
+    }
+
+    pub fn view(&self) -> ViewId {
+        self.view
+    }
+
+    /// Draw every visible layer of `map` overlapping the world-space rectangle `min..max`.
+    ///
+    /// `atlases` holds one texture per entry in `map.tilesets`, tiles whose tileset has no
+    /// atlas are skipped. Texture coordinates use each atlas's real size, so images padded
+    /// past the tile grid still line up. Returns the number of draw calls submitted.
+    pub fn draw(
+        &mut self,
+        renderer: &Renderer,
+        map: &TileMap,
+        atlases: &[&Texture],
+        program: &Program,
+        min: Vec2,
+        max: Vec2,
+    ) -> usize {
+        let projection = Mat4::orthographic_rh(min.x, max.x, max.y, min.y, -1.0, 1.0);
+        renderer.set_view_transform(self.view, &Mat4::IDENTITY, &projection);
+
+        let region = map.world_region(min, max);
+        let mut draw_calls = 0;
+
+        for layer in map.layers.iter().filter(|layer| layer.visible) {
+            let alpha = (layer.opacity.clamp(0.0, 1.0) * 255.0) as u32;
+            let abgr = (alpha << 24) | 0x00ff_ffff;
+
+            for (tileset, atlas) in map.tilesets.iter().zip(atlases) {
+                for (position, tile) in layer.region(region) {
+                    if !tileset.contains(tile.id) {
+                        continue;
+                    }
+
+                    let world = map.tile_to_world(position.as_ivec2());
+                    push_quad(
+                        &mut self.quads,
+                        tileset,
+                        atlas,
+                        tile.id,
+                        tile.flags,
+                        world,
+                        map.tile_size(),
+                        abgr,
+                    );
+                }
+
+                draw_calls += self
+                    .quads
+                    .submit(renderer, self.view, atlas.handle(), program);
+            }
+        }
+
+        draw_calls
+    }
+}
+
+fn push_quad(
+    quads: &mut QuadBatch,
+    tileset: &Tileset,
+    atlas: &Texture,
+    id: u32,
+    flags: TileFlags,
+    world: Vec2,
+    size: Vec2,
+    abgr: u32,
+) {
+    let origin = match tileset.atlas_position(id) {
+        Some(origin) => origin.as_vec2(),
+        None => return,
+    };
+    let atlas_size = Vec2::new(atlas.width() as f32, atlas.height() as f32);
+    let uv_min = origin / atlas_size;
+    let uv_size = Vec2::new(tileset.tile_width as f32, tileset.tile_height as f32) / atlas_size;
+
+    // Corners in clockwise order starting top-left, as offsets within the cell
+    let corners = [
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+    ];
+    let uvs = corners.map(|corner| {
+        let mut uv = corner;
+        if flags.contains(TileFlags::FLIP_DIAGONAL) {
+            uv = Vec2::new(uv.y, uv.x);
+        }
+        if flags.contains(TileFlags::FLIP_X) {
+            uv.x = 1.0 - uv.x;
+        }
+        if flags.contains(TileFlags::FLIP_Y) {
+            uv.y = 1.0 - uv.y;
+        }
+        uv_min + uv * uv_size
+    });
+
+    quads.push(corners.map(|corner| world + corner * size), uvs, abgr);
+}
@@ -0,0 +1,992 @@
+use super::{
+    cell_count, MapObject, ObjectLayer, ObjectShape, Properties, PropertyValue, Tile, TileFlags,
+    TileLayer, TileMap, Tileset,
+};
+use crate::error::MapError;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use glam::Vec2;
+use roxmltree::Node;
+use serde::Deserialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const ROTATED_HEXAGONAL: u32 = 0x1000_0000;
+const GID_MASK: u32 =
+    !(FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL);
+
+impl TileMap {
+    /// Load a Tiled map, picking the format from the file extension
+    pub fn load_tiled(path: impl AsRef<Path>) -> Result<Self, MapError> {
+        let path = path.as_ref();
+        match extension(path).as_deref() {
+            Some("tmj") | Some("json") => Self::load_tmj(path),
+            Some("tmx") => Self::load_tmx(path),
+            _ => Err(MapError::Format(path.to_path_buf())),
+        }
+    }
+
+    pub fn load_tmj(path: impl AsRef<Path>) -> Result<Self, MapError> {
+        let path = path.as_ref();
+        Self::parse_tmj(&read(path)?, parent(path))
+    }
+
+    pub fn load_tmx(path: impl AsRef<Path>) -> Result<Self, MapError> {
+        let path = path.as_ref();
+        Self::parse_tmx(&read(path)?, parent(path))
+    }
+
+    /// Parse a JSON map, external tilesets are resolved relative to `base_dir`
+    pub fn parse_tmj(source: &str, base_dir: &Path) -> Result<Self, MapError> {
+        let json: JsonMap = serde_json::from_str(source)?;
+        check_orientation(&json.orientation)?;
+        if json.infinite {
+            return Err(MapError::Unsupported("infinite maps".to_string()));
+        }
+
+        let mut map = TileMap::new(json.width, json.height, json.tilewidth, json.tileheight);
+        map.properties = json_properties(json.properties)?;
+
+        for tileset in json.tilesets {
+            map.add_tileset(json_tileset(tileset, base_dir)?);
+        }
+
+        for layer in json.layers {
+            add_json_layer(&mut map, layer, true, 1.0)?;
+        }
+
+        Ok(map)
+    }
+
+    /// Parse an XML map, external tilesets are resolved relative to `base_dir`
+    pub fn parse_tmx(source: &str, base_dir: &Path) -> Result<Self, MapError> {
+        let document = roxmltree::Document::parse(source)?;
+        let root = document.root_element();
+        expect_tag(root, "map")?;
+
+        check_orientation(root.attribute("orientation").unwrap_or("orthogonal"))?;
+        if parse_attr_or(root, "infinite", 0u8)? != 0 {
+            return Err(MapError::Unsupported("infinite maps".to_string()));
+        }
+
+        let mut map = TileMap::new(
+            parse_attr(root, "width")?,
+            parse_attr(root, "height")?,
+            parse_attr(root, "tilewidth")?,
+            parse_attr(root, "tileheight")?,
+        );
+        map.properties = xml_properties(root)?;
+
+        for tileset in children(root, "tileset") {
+            map.add_tileset(xml_map_tileset(tileset, base_dir)?);
+        }
+
+        add_xml_layers(&mut map, root, true, 1.0)?;
+
+        Ok(map)
+    }
+}
+
+impl Tileset {
+    /// Load an external Tiled tileset (.tsx or .tsj) starting at `first_id`
+    pub fn load_tiled(path: impl AsRef<Path>, first_id: u32) -> Result<Self, MapError> {
+        let path = path.as_ref();
+        let source = read(path)?;
+
+        let mut tileset = match extension(path).as_deref() {
+            Some("tsx") => {
+                let document = roxmltree::Document::parse(&source)?;
+                let root = document.root_element();
+                expect_tag(root, "tileset")?;
+                xml_tileset(root, parent(path))?
+            }
+            Some("tsj") | Some("json") => {
+                let json: JsonTileset = serde_json::from_str(&source)?;
+                json_embedded_tileset(json, parent(path))?
+            }
+            _ => return Err(MapError::Format(path.to_path_buf())),
+        };
+
+        tileset.first_id = first_id;
+        Ok(tileset)
+    }
+}
+
+fn read(path: &Path) -> Result<String, MapError> {
+    std::fs::read_to_string(path).map_err(|source| MapError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn parent(path: &Path) -> &Path {
+    path.parent().unwrap_or_else(|| Path::new(""))
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase())
+}
+
+fn check_orientation(orientation: &str) -> Result<(), MapError> {
+    match orientation {
+        "orthogonal" => Ok(()),
+        other => Err(MapError::Unsupported(format!("`{}` orientation", other))),
+    }
+}
+
+fn invalid(field: impl Into<String>, value: impl Into<String>) -> MapError {
+    MapError::InvalidValue {
+        field: field.into(),
+        value: value.into(),
+    }
+}
+
+/// Split a global id into the tile it refers to and its flip bits
+fn tile_from_gid(gid: u32) -> Tile {
+    let mut flags = TileFlags::NONE;
+    if gid & FLIPPED_HORIZONTALLY != 0 {
+        flags |= TileFlags::FLIP_X;
+    }
+    if gid & FLIPPED_VERTICALLY != 0 {
+        flags |= TileFlags::FLIP_Y;
+    }
+    if gid & FLIPPED_DIAGONALLY != 0 {
+        flags |= TileFlags::FLIP_DIAGONAL;
+    }
+
+    Tile::with_flags(gid & GID_MASK, flags)
+}
+
+/// Decode the payload of a tile layer into global ids
+fn decode_gids(
+    data: &str,
+    encoding: Option<&str>,
+    compression: Option<&str>,
+) -> Result<Vec<u32>, MapError> {
+    match encoding {
+        Some("csv") => data
+            .split(',')
+            .map(str::trim)
+            .filter(|gid| !gid.is_empty())
+            .map(|gid| gid.parse().map_err(|_| invalid("tile", gid)))
+            .collect(),
+        Some("base64") => {
+            let bytes = base64::decode(data.trim())?;
+            let bytes = decompress(bytes, compression)?;
+            if bytes.len() % 4 != 0 {
+                return Err(invalid("data", format!("{} bytes", bytes.len())));
+            }
+
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                .collect())
+        }
+        Some(other) => Err(MapError::Unsupported(format!("`{}` encoding", other))),
+        None => Err(MapError::Unsupported("unencoded string data".to_string())),
+    }
+}
+
+fn decompress(bytes: Vec<u8>, compression: Option<&str>) -> Result<Vec<u8>, MapError> {
+    let mut output = Vec::new();
+    let result = match compression {
+        None | Some("") => return Ok(bytes),
+        Some("zlib") => ZlibDecoder::new(bytes.as_slice()).read_to_end(&mut output),
+        Some("gzip") => GzDecoder::new(bytes.as_slice()).read_to_end(&mut output),
+        Some(other) => return Err(MapError::Unsupported(format!("`{}` compression", other))),
+    };
+    result.map_err(MapError::Decompress)?;
+
+    Ok(output)
+}
+
+/// Build a layer from decoded gids, fixed-size maps require every layer to match the map
+fn tile_layer(
+    map: &TileMap,
+    name: String,
+    width: u32,
+    height: u32,
+    gids: Vec<u32>,
+) -> Result<TileLayer, MapError> {
+    if (width, height) != (map.width(), map.height()) {
+        return Err(MapError::LayerSize {
+            layer: name,
+            width,
+            height,
+            map_width: map.width(),
+            map_height: map.height(),
+        });
+    }
+
+    let expected = cell_count(width, height)
+        .ok_or_else(|| invalid("layer size", format!("{}x{}", width, height)))?;
+    let actual = gids.len();
+    let tiles = gids.into_iter().map(tile_from_gid).collect();
+
+    TileLayer::from_tiles(name.clone(), width, height, tiles).ok_or(MapError::TileCount {
+        layer: name,
+        expected,
+        actual,
+    })
+}
+
+/// Parse `#RRGGBB` or `#AARRGGBB` into `0xAARRGGBB`
+fn parse_color(value: &str) -> Result<u32, MapError> {
+    let hex = value.trim_start_matches('#');
+    let color = u32::from_str_radix(hex, 16).map_err(|_| invalid("color", value))?;
+    match hex.len() {
+        6 => Ok(0xff00_0000 | color),
+        8 => Ok(color),
+        _ => Err(invalid("color", value)),
+    }
+}
+
+fn property_value(kind: &str, value: &str) -> Result<PropertyValue, MapError> {
+    let value = match kind {
+        "bool" => PropertyValue::Bool(value.parse().map_err(|_| invalid(kind, value))?),
+        "int" => PropertyValue::Int(value.parse().map_err(|_| invalid(kind, value))?),
+        "float" => PropertyValue::Float(value.parse().map_err(|_| invalid(kind, value))?),
+        "color" => PropertyValue::Color(parse_color(value)?),
+        "file" => PropertyValue::File(PathBuf::from(value)),
+        "object" => PropertyValue::Object(value.parse().map_err(|_| invalid(kind, value))?),
+        _ => PropertyValue::String(value.to_string()),
+    };
+
+    Ok(value)
+}
+
+// JSON (.tmj / .tsj)
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+fn default_orientation() -> String {
+    "orthogonal".to_string()
+}
+
+#[derive(Deserialize)]
+struct JsonMap {
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default = "default_orientation")]
+    orientation: String,
+    #[serde(default)]
+    infinite: bool,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+    #[serde(default)]
+    tilesets: Vec<JsonTileset>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum JsonLayer {
+    TileLayer {
+        name: String,
+        width: u32,
+        height: u32,
+        data: JsonData,
+        encoding: Option<String>,
+        compression: Option<String>,
+        #[serde(default = "default_true")]
+        visible: bool,
+        #[serde(default = "default_opacity")]
+        opacity: f32,
+        #[serde(default)]
+        properties: Vec<JsonProperty>,
+    },
+    ObjectGroup {
+        name: String,
+        #[serde(default)]
+        objects: Vec<JsonObject>,
+        #[serde(default = "default_true")]
+        visible: bool,
+        #[serde(default = "default_opacity")]
+        opacity: f32,
+        #[serde(default)]
+        properties: Vec<JsonProperty>,
+    },
+    Group {
+        #[serde(default)]
+        layers: Vec<JsonLayer>,
+        #[serde(default = "default_true")]
+        visible: bool,
+        #[serde(default = "default_opacity")]
+        opacity: f32,
+    },
+    /// Image layers carry nothing the tile map can represent
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonData {
+    Gids(Vec<u32>),
+    Encoded(String),
+}
+
+#[derive(Deserialize)]
+struct JsonTileset {
+    #[serde(default)]
+    firstgid: u32,
+    source: Option<String>,
+    name: Option<String>,
+    tilewidth: Option<u32>,
+    tileheight: Option<u32>,
+    columns: Option<u32>,
+    tilecount: Option<u32>,
+    #[serde(default)]
+    spacing: u32,
+    #[serde(default)]
+    margin: u32,
+    image: Option<String>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Deserialize)]
+struct JsonObject {
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type", alias = "class")]
+    kind: String,
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    rotation: f32,
+    gid: Option<u32>,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(default)]
+    ellipse: bool,
+    #[serde(default)]
+    point: bool,
+    polygon: Option<Vec<JsonPoint>>,
+    polyline: Option<Vec<JsonPoint>>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Deserialize)]
+struct JsonPoint {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct JsonProperty {
+    name: String,
+    #[serde(default, rename = "type")]
+    kind: String,
+    value: serde_json::Value,
+}
+
+fn json_properties(properties: Vec<JsonProperty>) -> Result<Properties, MapError> {
+    properties
+        .into_iter()
+        .map(|property| -> Result<_, MapError> {
+            let value = match property.value {
+                serde_json::Value::String(value) => value,
+                other => other.to_string(),
+            };
+            Ok((property.name, property_value(&property.kind, &value)?))
+        })
+        .collect()
+}
+
+fn json_tileset(tileset: JsonTileset, base_dir: &Path) -> Result<Tileset, MapError> {
+    match &tileset.source {
+        Some(source) => Tileset::load_tiled(base_dir.join(source), tileset.firstgid),
+        None => json_embedded_tileset(tileset, base_dir),
+    }
+}
+
+fn json_embedded_tileset(tileset: JsonTileset, base_dir: &Path) -> Result<Tileset, MapError> {
+    let missing = |attribute| MapError::MissingAttribute {
+        element: "tileset".to_string(),
+        attribute,
+    };
+
+    let mut result = Tileset::new(
+        tileset.name.unwrap_or_default(),
+        tileset.firstgid,
+        tileset.tilewidth.ok_or_else(|| missing("tilewidth"))?,
+        tileset.tileheight.ok_or_else(|| missing("tileheight"))?,
+        tileset.columns.ok_or_else(|| missing("columns"))?,
+        tileset.tilecount.ok_or_else(|| missing("tilecount"))?,
+    );
+    result.spacing = tileset.spacing;
+    result.margin = tileset.margin;
+    result.image = tileset.image.map(|image| base_dir.join(image));
+    result.properties = json_properties(tileset.properties)?;
+
+    Ok(result)
+}
+
+fn add_json_layer(
+    map: &mut TileMap,
+    layer: JsonLayer,
+    parent_visible: bool,
+    parent_opacity: f32,
+) -> Result<(), MapError> {
+    match layer {
+        JsonLayer::TileLayer {
+            name,
+            width,
+            height,
+            data,
+            encoding,
+            compression,
+            visible,
+            opacity,
+            properties,
+        } => {
+            let gids = match data {
+                JsonData::Gids(gids) => gids,
+                JsonData::Encoded(data) => {
+                    decode_gids(&data, encoding.as_deref(), compression.as_deref())?
+                }
+            };
+
+            let mut layer = tile_layer(map, name, width, height, gids)?;
+            layer.visible = parent_visible && visible;
+            layer.opacity = parent_opacity * opacity;
+            layer.properties = json_properties(properties)?;
+            map.layers.push(layer);
+        }
+        JsonLayer::ObjectGroup {
+            name,
+            objects,
+            visible,
+            opacity,
+            properties,
+        } => {
+            map.object_layers.push(ObjectLayer {
+                name,
+                visible: parent_visible && visible,
+                opacity: parent_opacity * opacity,
+                objects: objects
+                    .into_iter()
+                    .map(json_object)
+                    .collect::<Result<_, _>>()?,
+                properties: json_properties(properties)?,
+            });
+        }
+        JsonLayer::Group {
+            layers,
+            visible,
+            opacity,
+        } => {
+            for layer in layers {
+                add_json_layer(
+                    map,
+                    layer,
+                    parent_visible && visible,
+                    parent_opacity * opacity,
+                )?;
+            }
+        }
+        JsonLayer::Other => {}
+    }
+
+    Ok(())
+}
+
+fn json_object(object: JsonObject) -> Result<MapObject, MapError> {
+    let points = |points: Vec<JsonPoint>| -> Vec<Vec2> {
+        points
+            .into_iter()
+            .map(|point| Vec2::new(point.x, point.y))
+            .collect()
+    };
+
+    let shape = if let Some(polygon) = object.polygon {
+        ObjectShape::Polygon(points(polygon))
+    } else if let Some(polyline) = object.polyline {
+        ObjectShape::Polyline(points(polyline))
+    } else if object.ellipse {
+        ObjectShape::Ellipse
+    } else if object.point {
+        ObjectShape::Point
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    Ok(MapObject {
+        id: object.id,
+        name: object.name,
+        kind: object.kind,
+        position: Vec2::new(object.x, object.y),
+        size: Vec2::new(object.width, object.height),
+        rotation: object.rotation,
+        visible: object.visible,
+        shape,
+        tile: object.gid.map(tile_from_gid),
+        properties: json_properties(object.properties)?,
+    })
+}
+
+// XML (.tmx / .tsx)
+
+fn expect_tag(node: Node, tag: &str) -> Result<(), MapError> {
+    match node.tag_name().name() {
+        name if name == tag => Ok(()),
+        name => Err(invalid("root element", name)),
+    }
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    tag: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |child| child.has_tag_name(tag))
+}
+
+fn attr<'a>(node: Node<'a, '_>, attribute: &'static str) -> Result<&'a str, MapError> {
+    node.attribute(attribute)
+        .ok_or_else(|| MapError::MissingAttribute {
+            element: node.tag_name().name().to_string(),
+            attribute,
+        })
+}
+
+fn parse_attr<T: FromStr>(node: Node, attribute: &'static str) -> Result<T, MapError> {
+    let value = attr(node, attribute)?;
+    value.parse().map_err(|_| invalid(attribute, value))
+}
+
+fn parse_attr_or<T: FromStr>(
+    node: Node,
+    attribute: &'static str,
+    default: T,
+) -> Result<T, MapError> {
+    match node.attribute(attribute) {
+        Some(value) => value.parse().map_err(|_| invalid(attribute, value)),
+        None => Ok(default),
+    }
+}
+
+fn xml_properties(node: Node) -> Result<Properties, MapError> {
+    let mut properties = Properties::new();
+    for property in children(node, "properties").flat_map(|node| children(node, "property")) {
+        let kind = property.attribute("type").unwrap_or("string");
+        // Multi-line strings are stored as text instead of the `value` attribute
+        let value = match property.attribute("value") {
+            Some(value) => value,
+            None => property.text().unwrap_or_default(),
+        };
+        properties.insert(
+            attr(property, "name")?.to_string(),
+            property_value(kind, value)?,
+        );
+    }
+
+    Ok(properties)
+}
+
+fn xml_map_tileset(node: Node, base_dir: &Path) -> Result<Tileset, MapError> {
+    let first_id = parse_attr(node, "firstgid")?;
+    match node.attribute("source") {
+        Some(source) => Tileset::load_tiled(base_dir.join(source), first_id),
+        None => {
+            let mut tileset = xml_tileset(node, base_dir)?;
+            tileset.first_id = first_id;
+            Ok(tileset)
+        }
+    }
+}
+
+fn xml_tileset(node: Node, base_dir: &Path) -> Result<Tileset, MapError> {
+    let mut tileset = Tileset::new(
+        node.attribute("name").unwrap_or_default(),
+        0,
+        parse_attr(node, "tilewidth")?,
+        parse_attr(node, "tileheight")?,
+        parse_attr(node, "columns")?,
+        parse_attr(node, "tilecount")?,
+    );
+    tileset.spacing = parse_attr_or(node, "spacing", 0)?;
+    tileset.margin = parse_attr_or(node, "margin", 0)?;
+    tileset.properties = xml_properties(node)?;
+
+    if let Some(image) = children(node, "image").next() {
+        tileset.image = Some(base_dir.join(attr(image, "source")?));
+    }
+
+    Ok(tileset)
+}
+
+fn add_xml_layers(
+    map: &mut TileMap,
+    parent: Node,
+    parent_visible: bool,
+    parent_opacity: f32,
+) -> Result<(), MapError> {
+    for node in parent.children().filter(Node::is_element) {
+        let visible = parent_visible && parse_attr_or(node, "visible", 1u8)? != 0;
+        let opacity = parent_opacity * parse_attr_or(node, "opacity", 1.0f32)?;
+
+        match node.tag_name().name() {
+            "layer" => {
+                let mut layer = xml_tile_layer(map, node)?;
+                layer.visible = visible;
+                layer.opacity = opacity;
+                map.layers.push(layer);
+            }
+            "objectgroup" => {
+                map.object_layers.push(ObjectLayer {
+                    name: node.attribute("name").unwrap_or_default().to_string(),
+                    visible,
+                    opacity,
+                    objects: children(node, "object")
+                        .map(xml_object)
+                        .collect::<Result<_, _>>()?,
+                    properties: xml_properties(node)?,
+                });
+            }
+            "group" => add_xml_layers(map, node, visible, opacity)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn xml_tile_layer(map: &TileMap, node: Node) -> Result<TileLayer, MapError> {
+    let name = node.attribute("name").unwrap_or_default().to_string();
+    let width = parse_attr(node, "width")?;
+    let height = parse_attr(node, "height")?;
+
+    let data = children(node, "data")
+        .next()
+        .ok_or_else(|| MapError::MissingAttribute {
+            element: "layer".to_string(),
+            attribute: "data",
+        })?;
+
+    let gids = match data.attribute("encoding") {
+        // Without an encoding every tile is its own element
+        None => children(data, "tile")
+            .map(|tile| parse_attr_or(tile, "gid", 0))
+            .collect::<Result<_, _>>()?,
+        encoding => decode_gids(
+            data.text().unwrap_or_default(),
+            encoding,
+            data.attribute("compression"),
+        )?,
+    };
+
+    let mut layer = tile_layer(map, name, width, height, gids)?;
+    layer.properties = xml_properties(node)?;
+    Ok(layer)
+}
+
+fn xml_object(node: Node) -> Result<MapObject, MapError> {
+    let points = |node: Node| -> Result<Vec<Vec2>, MapError> {
+        attr(node, "points")?
+            .split_whitespace()
+            .map(|point| -> Result<Vec2, MapError> {
+                let (x, y) = point
+                    .split_once(',')
+                    .ok_or_else(|| invalid("points", point))?;
+                let x = x.parse().map_err(|_| invalid("points", point))?;
+                let y = y.parse().map_err(|_| invalid("points", point))?;
+                Ok(Vec2::new(x, y))
+            })
+            .collect()
+    };
+
+    let mut shape = ObjectShape::Rectangle;
+    for child in node.children().filter(Node::is_element) {
+        shape = match child.tag_name().name() {
+            "ellipse" => ObjectShape::Ellipse,
+            "point" => ObjectShape::Point,
+            "polygon" => ObjectShape::Polygon(points(child)?),
+            "polyline" => ObjectShape::Polyline(points(child)?),
+            _ => continue,
+        };
+    }
+
+    let kind = node
+        .attribute("class")
+        .or_else(|| node.attribute("type"))
+        .unwrap_or_default();
+
+    Ok(MapObject {
+        id: parse_attr_or(node, "id", 0)?,
+        name: node.attribute("name").unwrap_or_default().to_string(),
+        kind: kind.to_string(),
+        position: Vec2::new(
+            parse_attr_or(node, "x", 0.0)?,
+            parse_attr_or(node, "y", 0.0)?,
+        ),
+        size: Vec2::new(
+            parse_attr_or(node, "width", 0.0)?,
+            parse_attr_or(node, "height", 0.0)?,
+        ),
+        rotation: parse_attr_or(node, "rotation", 0.0)?,
+        visible: parse_attr_or(node, "visible", 1u8)? != 0,
+        shape,
+        tile: match node.attribute("gid") {
+            Some(_) => Some(tile_from_gid(parse_attr(node, "gid")?)),
+            None => None,
+        },
+        properties: xml_properties(node)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::UVec2;
+
+    const TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="3" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <properties>
+  <property name="music" value="town.ogg"/>
+ </properties>
+ <tileset firstgid="1" name="terrain" tilewidth="16" tileheight="16" spacing="1" tilecount="4" columns="2">
+  <image source="terrain.png" width="33" height="33"/>
+ </tileset>
+ <layer id="1" name="ground" width="3" height="2">
+  <data encoding="csv">
+1,2147483650,0,
+1073741827,536870913,3758096388
+</data>
+ </layer>
+ <group name="details" opacity="0.5">
+  <layer id="2" name="decor" width="3" height="2" visible="0">
+   <data encoding="base64">AQAAAAAAAAAAAAAAAAAAAAAAAAACAAAA</data>
+  </layer>
+ </group>
+ <objectgroup name="spawns">
+  <object id="7" name="player" type="spawn" x="8" y="24">
+   <point/>
+  </object>
+ </objectgroup>
+</map>"#;
+
+    const TMJ: &str = r#"{
+        "width": 2,
+        "height": 2,
+        "tilewidth": 8,
+        "tileheight": 8,
+        "orientation": "orthogonal",
+        "tilesets": [
+            {"firstgid": 1, "name": "tiles", "tilewidth": 8, "tileheight": 8,
+             "columns": 4, "tilecount": 8, "image": "tiles.png"}
+        ],
+        "layers": [
+            {"type": "tilelayer", "name": "ground", "width": 2, "height": 2,
+             "data": [5, 2147483649, 0, 1073741826],
+             "properties": [{"name": "solid", "type": "bool", "value": true}]},
+            {"type": "objectgroup", "name": "zones", "objects": [
+                {"id": 1, "name": "exit", "x": 4, "y": 4, "width": 8, "height": 8,
+                 "polygon": [{"x": 0, "y": 0}, {"x": 8, "y": 0}, {"x": 0, "y": 8}]}
+            ]},
+            {"type": "imagelayer", "name": "sky"}
+        ]
+    }"#;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/maps")
+            .join(name)
+    }
+
+    fn tile(layer: &TileLayer, x: u32, y: u32) -> Tile {
+        layer.get(UVec2::new(x, y)).unwrap()
+    }
+
+    #[test]
+    fn gid_flip_bits_become_flags() {
+        assert_eq!(tile_from_gid(5), Tile::new(5));
+        assert_eq!(
+            tile_from_gid(0x8000_0002),
+            Tile::with_flags(2, TileFlags::FLIP_X)
+        );
+        assert_eq!(
+            tile_from_gid(0xe000_0004),
+            Tile::with_flags(
+                4,
+                TileFlags::FLIP_X | TileFlags::FLIP_Y | TileFlags::FLIP_DIAGONAL
+            )
+        );
+        // The hexagonal rotation bit is dropped on orthogonal maps
+        assert_eq!(tile_from_gid(0x1000_0001), Tile::new(1));
+    }
+
+    #[test]
+    fn parses_tmx() {
+        let map = TileMap::parse_tmx(TMX, Path::new("maps")).unwrap();
+        assert_eq!((map.width(), map.height()), (3, 2));
+        assert_eq!(
+            map.properties.get("music"),
+            Some(&PropertyValue::String("town.ogg".to_string()))
+        );
+
+        let tileset = &map.tilesets[0];
+        assert_eq!((tileset.first_id, tileset.spacing), (1, 1));
+        assert_eq!(tileset.image, Some(Path::new("maps").join("terrain.png")));
+
+        let ground = map.layer_by_name("ground").unwrap();
+        assert_eq!(tile(ground, 0, 0), Tile::new(1));
+        assert_eq!(tile(ground, 1, 0), Tile::with_flags(2, TileFlags::FLIP_X));
+        assert_eq!(tile(ground, 2, 0), Tile::EMPTY);
+        assert_eq!(tile(ground, 0, 1), Tile::with_flags(3, TileFlags::FLIP_Y));
+        assert_eq!(
+            tile(ground, 1, 1),
+            Tile::with_flags(1, TileFlags::FLIP_DIAGONAL)
+        );
+        assert_eq!(
+            tile(ground, 2, 1),
+            Tile::with_flags(
+                4,
+                TileFlags::FLIP_X | TileFlags::FLIP_Y | TileFlags::FLIP_DIAGONAL
+            )
+        );
+
+        let decor = map.layer_by_name("decor").unwrap();
+        assert!(!decor.visible);
+        assert_eq!(decor.opacity, 0.5);
+        assert_eq!(tile(decor, 0, 0), Tile::new(1));
+        assert_eq!(tile(decor, 2, 1), Tile::new(2));
+
+        let player = map
+            .object_layer_by_name("spawns")
+            .and_then(|layer| layer.object_by_name("player"))
+            .unwrap();
+        assert_eq!(player.kind, "spawn");
+        assert_eq!(player.position, Vec2::new(8.0, 24.0));
+        assert_eq!(player.shape, ObjectShape::Point);
+    }
+
+    #[test]
+    fn parses_tmj() {
+        let map = TileMap::parse_tmj(TMJ, Path::new("")).unwrap();
+        assert_eq!(map.tile_size(), Vec2::new(8.0, 8.0));
+        assert_eq!(map.tilesets[0].tile_count, 8);
+
+        let ground = map.layer(0).unwrap();
+        assert_eq!(tile(ground, 0, 0), Tile::new(5));
+        assert_eq!(tile(ground, 1, 0), Tile::with_flags(1, TileFlags::FLIP_X));
+        assert_eq!(tile(ground, 1, 1), Tile::with_flags(2, TileFlags::FLIP_Y));
+        assert_eq!(
+            ground.properties.get("solid"),
+            Some(&PropertyValue::Bool(true))
+        );
+
+        let exit = map.object_layers[0].object_by_name("exit").unwrap();
+        match &exit.shape {
+            ObjectShape::Polygon(points) => assert_eq!(points.len(), 3),
+            other => panic!("expected a polygon, got {:?}", other),
+        }
+        // Image layers are skipped
+        assert_eq!(map.layers.len(), 1);
+    }
+
+    #[test]
+    fn rejects_layers_that_differ_from_the_map() {
+        let source = TMX.replacen(
+            r#"name="ground" width="3""#,
+            r#"name="ground" width="4""#,
+            1,
+        );
+        assert!(matches!(
+            TileMap::parse_tmx(&source, Path::new("")),
+            Err(MapError::LayerSize {
+                width: 4,
+                height: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_huge_dimensions_without_allocating() {
+        let source = TMJ
+            .replace(r#""width": 2"#, r#""width": 4294967295"#)
+            .replace(r#""height": 2"#, r#""height": 4294967295"#);
+        assert!(matches!(
+            TileMap::parse_tmj(&source, Path::new("")),
+            Err(MapError::InvalidValue { field, .. }) if field == "layer size"
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let source = TMJ.replace("[5, 2147483649, 0, 1073741826]", "[5, 1]");
+        assert!(matches!(
+            TileMap::parse_tmj(&source, Path::new("")),
+            Err(MapError::TileCount {
+                expected: 4,
+                actual: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn loads_compressed_layers() {
+        let map = TileMap::load_tiled(fixture("compressed.tmx")).unwrap();
+
+        let ground = map.layer_by_name("ground").unwrap();
+        assert_eq!(tile(ground, 0, 0), Tile::new(1));
+        assert_eq!(tile(ground, 2, 0), Tile::EMPTY);
+        assert_eq!(tile(ground, 1, 1), Tile::with_flags(4, TileFlags::FLIP_X));
+
+        let decor = map.layer_by_name("decor").unwrap();
+        assert_eq!(tile(decor, 2, 0), Tile::new(2));
+        assert_eq!(tile(decor, 0, 1), Tile::new(4));
+        assert_eq!(tile(decor, 2, 1), Tile::with_flags(3, TileFlags::FLIP_Y));
+    }
+
+    #[test]
+    fn resolves_external_tilesets_relative_to_the_map() {
+        let map = TileMap::load_tiled(fixture("compressed.tmx")).unwrap();
+
+        let tileset = &map.tilesets[0];
+        assert_eq!(tileset.name, "terrain");
+        assert_eq!((tileset.first_id, tileset.tile_count), (1, 4));
+        assert_eq!(tileset.image, Some(fixture("tilesets/terrain.png")));
+        assert_eq!(
+            tileset.properties.get("biome"),
+            Some(&PropertyValue::String("grass".to_string()))
+        );
+    }
+
+    #[test]
+    fn missing_external_tilesets_report_their_path() {
+        let source = std::fs::read_to_string(fixture("compressed.tmx")).unwrap();
+        let source = source.replace("terrain.tsx", "missing.tsx");
+        match TileMap::parse_tmx(&source, &fixture("")) {
+            Err(MapError::Io { path, .. }) => {
+                assert_eq!(path, fixture("tilesets/missing.tsx"))
+            }
+            other => panic!("expected an io error, got {:?}", other),
+        }
+    }
+}
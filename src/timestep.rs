@@ -0,0 +1,142 @@
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_UPDATE_RATE: u32 = 60;
+/// Longest frame fed into the accumulator, anything slower is treated as this long
+pub const DEFAULT_MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+/// Accumulator turning variable frame times into a whole number of fixed updates
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    step: Duration,
+    max_frame_time: Duration,
+    accumulator: Duration,
+    last: Option<Instant>,
+}
+
+impl FixedTimestep {
+    pub fn new(update_rate: u32) -> Self {
+        Self {
+            step: Duration::from_secs(1) / update_rate.max(1),
+            max_frame_time: DEFAULT_MAX_FRAME_TIME,
+            accumulator: Duration::ZERO,
+            last: None,
+        }
+    }
+
+    pub fn with_max_frame_time(mut self, max_frame_time: Duration) -> Self {
+        self.max_frame_time = max_frame_time;
+        self
+    }
+
+    /// Duration of a single update
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// Forget any accumulated time and restart the clock
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::ZERO;
+        self.last = None;
+    }
+
+    /// Measure the time since the previous call and return how many updates to run
+    pub fn tick(&mut self) -> u32 {
+        let now = Instant::now();
+        let frame_time = match self.last.replace(now) {
+            Some(last) => now - last,
+            None => Duration::ZERO,
+        };
+
+        self.advance(frame_time)
+    }
+
+    /// Feed `frame_time` into the accumulator and return how many updates to run
+    pub fn advance(&mut self, frame_time: Duration) -> u32 {
+        // Clamp so a long stall can't demand more updates than we can run in a frame
+        self.accumulator += frame_time.min(self.max_frame_time);
+        self.drain()
+    }
+
+    /// Feed exactly one step into the accumulator, bypassing the stall clamp so a frame
+    /// always runs an update whatever the maximum frame time
+    pub fn advance_step(&mut self) -> u32 {
+        self.accumulator += self.step;
+        self.drain()
+    }
+
+    /// Consume whole steps from the accumulator, returning how many were taken
+    fn drain(&mut self) -> u32 {
+        let mut updates = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            updates += 1;
+        }
+
+        updates
+    }
+
+    /// How far between the last and next update the current frame lies, in `0.0..1.0`
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(DEFAULT_UPDATE_RATE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_one_update_per_elapsed_step() {
+        let mut timestep = FixedTimestep::new(50);
+        assert_eq!(timestep.advance(Duration::from_millis(60)), 3);
+        assert_eq!(timestep.advance(Duration::from_millis(10)), 0);
+    }
+
+    #[test]
+    fn leftover_time_carries_into_the_next_frame() {
+        let mut timestep = FixedTimestep::new(50);
+        assert_eq!(timestep.advance(Duration::from_millis(30)), 1);
+        assert_eq!(timestep.advance(Duration::from_millis(10)), 1);
+        assert_eq!(timestep.advance(Duration::from_millis(15)), 0);
+        assert_eq!(timestep.advance(Duration::from_millis(5)), 1);
+    }
+
+    #[test]
+    fn stalls_are_clamped_to_the_max_frame_time() {
+        let mut timestep = FixedTimestep::new(100).with_max_frame_time(Duration::from_millis(50));
+        assert_eq!(timestep.advance(Duration::from_secs(10)), 5);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn alpha_is_the_fraction_of_a_step_left_over() {
+        let mut timestep = FixedTimestep::new(50);
+        for millis in [5, 13, 20, 37, 1] {
+            timestep.advance(Duration::from_millis(millis));
+            let alpha = timestep.alpha();
+            assert!((0.0..1.0).contains(&alpha), "alpha {} out of range", alpha);
+        }
+
+        timestep.reset();
+        timestep.advance(Duration::from_millis(25));
+        assert!((timestep.alpha() - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn advance_step_ignores_a_max_frame_time_below_the_step() {
+        let mut timestep = FixedTimestep::new(10).with_max_frame_time(Duration::from_millis(1));
+        assert_eq!(timestep.advance(timestep.step()), 0);
+
+        timestep.reset();
+        for _ in 0..3 {
+            assert_eq!(timestep.advance_step(), 1);
+        }
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+}
@@ -0,0 +1,277 @@
+use crate::platform::Resolution;
+use crate::renderer::Renderer;
+use bgfx_rs::static_lib::{ClearFlags, ViewMode};
+use glam::{Mat4, Vec4};
+
+pub type ViewId = u16;
+
+/// Where a pass is submitted relative to the others, earlier stages draw first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    World,
+    /// Full screen effects applied to the world before the interface is drawn
+    PostProcess,
+    Ui,
+    DebugOverlay,
+}
+
+/// Area of the backbuffer a pass renders to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViewRect {
+    Full,
+    /// `x`, `y`, `width` and `height` as fractions of the backbuffer, for split screen
+    Scaled(Vec4),
+    /// Fixed pixel rectangle that ignores resizes
+    Fixed {
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+    },
+}
+
+impl ViewRect {
+    /// Pixel rectangle `(x, y, width, height)` inside a backbuffer of `resolution`
+    pub fn resolve(self, resolution: Resolution) -> (u16, u16, u16, u16) {
+        let (width, height) = (resolution.width as f32, resolution.height as f32);
+        match self {
+            ViewRect::Full => (0, 0, resolution.width as u16, resolution.height as u16),
+            ViewRect::Scaled(rect) => (
+                (rect.x * width) as u16,
+                (rect.y * height) as u16,
+                (rect.z * width) as u16,
+                (rect.w * height) as u16,
+            ),
+            ViewRect::Fixed {
+                x,
+                y,
+                width,
+                height,
+            } => (x, y, width, height),
+        }
+    }
+}
+
+/// One named bgfx view and the state applied to it every frame
+#[derive(Clone, Debug)]
+pub struct Pass {
+    name: String,
+    stage: Stage,
+    clear: Option<(ClearFlags, u32)>,
+    rect: ViewRect,
+    transform: Option<(Mat4, Mat4)>,
+    mode: Option<ViewMode>,
+}
+
+impl Pass {
+    pub fn new(name: impl Into<String>, stage: Stage) -> Self {
+        Self {
+            name: name.into(),
+            stage,
+            clear: None,
+            rect: ViewRect::Full,
+            transform: None,
+            mode: None,
+        }
+    }
+
+    /// Clear the buffers in `flags` before drawing, `rgba` is `0xRRGGBBAA`
+    pub fn with_clear(mut self, flags: ClearFlags, rgba: u32) -> Self {
+        self.clear = Some((flags, rgba));
+        self
+    }
+
+    pub fn with_rect(mut self, rect: ViewRect) -> Self {
+        self.rect = rect;
+        self
+    }
+
+    /// View and projection matrices, passes without one keep whatever was set on the view
+    pub fn with_transform(mut self, view: Mat4, projection: Mat4) -> Self {
+        self.transform = Some((view, projection));
+        self
+    }
+
+    /// Draw call ordering within the pass, such as `ViewMode::Sequential` for 2D
+    pub fn with_mode(mut self, mode: ViewMode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    pub fn rect(&self) -> ViewRect {
+        self.rect
+    }
+
+    pub fn set_rect(&mut self, rect: ViewRect) {
+        self.rect = rect;
+    }
+
+    pub fn set_transform(&mut self, view: Mat4, projection: Mat4) {
+        self.transform = Some((view, projection));
+    }
+
+    pub fn set_clear(&mut self, clear: Option<(ClearFlags, u32)>) {
+        self.clear = clear;
+    }
+}
+
+/// Assigns bgfx view ids to named passes and submits them in stage order, keeping their
+/// rects in sync with the backbuffer.
+///
+/// A pass keeps its view id until it is removed, adding or removing other passes only
+/// changes the submission order.
+#[derive(Clone, Debug, Default)]
+pub struct RenderGraph {
+    /// Passes with their view ids, in submission order
+    passes: Vec<(ViewId, Pass)>,
+    resolution: Resolution,
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `pass` after every pass of the same or an earlier stage and return its view id.
+    /// A pass with the same name is replaced and its id reused, otherwise the lowest free
+    /// id is taken.
+    pub fn add(&mut self, pass: Pass) -> ViewId {
+        let view = match self.position(&pass.name) {
+            Some(index) => self.passes.remove(index).0,
+            None => (0..)
+                .find(|view| self.passes.iter().all(|(used, _)| used != view))
+                .unwrap_or_default(),
+        };
+
+        let index = self
+            .passes
+            .iter()
+            .position(|(_, existing)| existing.stage > pass.stage)
+            .unwrap_or(self.passes.len());
+        self.passes.insert(index, (view, pass));
+        view
+    }
+
+    /// Remove the pass called `name`, its view id may be handed out again
+    pub fn remove(&mut self, name: &str) -> Option<Pass> {
+        let index = self.position(name)?;
+        Some(self.passes.remove(index).1)
+    }
+
+    /// View id of the pass called `name`
+    pub fn view(&self, name: &str) -> Option<ViewId> {
+        self.position(name).map(|index| self.passes[index].0)
+    }
+
+    pub fn pass(&self, name: &str) -> Option<&Pass> {
+        self.position(name).map(|index| &self.passes[index].1)
+    }
+
+    pub fn pass_mut(&mut self, name: &str) -> Option<&mut Pass> {
+        let index = self.position(name)?;
+        Some(&mut self.passes[index].1)
+    }
+
+    /// Passes with their view ids, in submission order
+    pub fn iter(&self) -> impl Iterator<Item = (ViewId, &Pass)> {
+        self.passes.iter().map(|(view, pass)| (*view, pass))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.passes.iter().position(|(_, pass)| pass.name == name)
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Track a new backbuffer size, takes effect on the next `RenderGraph::apply`
+    pub fn resize(&mut self, resolution: Resolution) {
+        self.resolution = resolution;
+    }
+
+    /// Configure every view for the current frame. Views that clear are touched so they
+    /// are cleared even when nothing is submitted to them.
+    pub fn apply(&self, renderer: &Renderer) {
+        let order: Vec<ViewId> = self.passes.iter().map(|(view, _)| *view).collect();
+        renderer.set_view_order(&order);
+
+        for (view, pass) in self.iter() {
+            let (x, y, width, height) = pass.rect.resolve(self.resolution);
+            renderer.set_view_rect(view, x, y, Resolution::new(width as u32, height as u32));
+
+            if let Some((flags, rgba)) = pass.clear {
+                renderer.set_view_clear(view, flags, rgba);
+                renderer.touch(view);
+            }
+            if let Some((view_matrix, projection)) = &pass.transform {
+                renderer.set_view_transform(view, view_matrix, projection);
+            }
+            if let Some(mode) = pass.mode {
+                renderer.set_view_mode(view, mode);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(graph: &RenderGraph) -> Vec<(ViewId, &str)> {
+        graph
+            .iter()
+            .map(|(view, pass)| (view, pass.name()))
+            .collect()
+    }
+
+    #[test]
+    fn ids_survive_adding_earlier_stages() {
+        let mut graph = RenderGraph::new();
+        let ui = graph.add(Pass::new("ui", Stage::Ui));
+        let world = graph.add(Pass::new("world", Stage::World));
+
+        assert_eq!((ui, world), (0, 1));
+        assert_eq!(names(&graph), [(1, "world"), (0, "ui")]);
+        assert_eq!(graph.view("ui"), Some(ui));
+    }
+
+    #[test]
+    fn ids_survive_removing_other_passes() {
+        let mut graph = RenderGraph::new();
+        graph.add(Pass::new("world", Stage::World));
+        let sprites = graph.add(Pass::new("sprites", Stage::World));
+        let debug = graph.add(Pass::new("debug", Stage::DebugOverlay));
+
+        assert!(graph.remove("world").is_some());
+        assert_eq!(graph.view("sprites"), Some(sprites));
+        assert_eq!(graph.view("debug"), Some(debug));
+
+        // The freed id is reused by the next new pass
+        assert_eq!(graph.add(Pass::new("bloom", Stage::PostProcess)), 0);
+        assert_eq!(names(&graph), [(1, "sprites"), (0, "bloom"), (2, "debug")]);
+    }
+
+    #[test]
+    fn replacing_a_pass_keeps_its_id() {
+        let mut graph = RenderGraph::new();
+        graph.add(Pass::new("world", Stage::World));
+        let overlay = graph.add(Pass::new("overlay", Stage::Ui));
+        graph.add(Pass::new("hud", Stage::Ui));
+
+        assert_eq!(
+            graph.add(Pass::new("overlay", Stage::DebugOverlay)),
+            overlay
+        );
+        assert_eq!(names(&graph), [(0, "world"), (2, "hud"), (1, "overlay")]);
+        assert_eq!(graph.pass("overlay").unwrap().stage(), Stage::DebugOverlay);
+    }
+}
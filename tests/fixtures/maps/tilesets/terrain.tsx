@@ -0,0 +1,7 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" name="terrain" tilewidth="16" tileheight="16" spacing="1" tilecount="4" columns="2">
+ <properties>
+  <property name="biome" value="grass"/>
+ </properties>
+ <image source="terrain.png" width="33" height="33"/>
+</tileset>
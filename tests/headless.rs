@@ -0,0 +1,80 @@
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+/// Long enough for a slow CI machine, short enough that a missed frame limit fails the run
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The binary with a clean environment: headless, stopping after `frames`, and a config
+/// path that doesn't exist so the defaults are used
+fn headless(frames: &str) -> Command {
+    let config =
+        std::env::temp_dir().join(format!("glfw-test-missing-{}.toml", std::process::id()));
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_glfw-test"));
+    command
+        .env("GLFW_TEST_HEADLESS", "1")
+        .env("GLFW_TEST_FRAMES", frames)
+        .env("GLFW_TEST_CONFIG", config)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    command
+}
+
+/// Run `command`, killing it if it outlives `TIMEOUT`
+fn run(mut command: Command) -> Output {
+    let mut child = command.spawn().expect("failed to start the binary");
+    let start = Instant::now();
+    while child
+        .try_wait()
+        .expect("failed to poll the binary")
+        .is_none()
+    {
+        if start.elapsed() > TIMEOUT {
+            child.kill().ok();
+            panic!("still running after {:?}", TIMEOUT);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    child.wait_with_output().expect("failed to collect output")
+}
+
+fn assert_success(output: &Output) {
+    assert!(
+        output.status.success(),
+        "exited with {:?}\nstderr:\n{}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn runs_a_few_frames_and_exits() {
+    assert_success(&run(headless("5")));
+}
+
+#[test]
+fn frames_flag_overrides_the_environment() {
+    let mut command = headless("1000000");
+    command.arg("--frames").arg("3");
+    assert_success(&run(command));
+}
+
+#[test]
+fn invalid_arguments_exit_with_the_cli_code() {
+    let mut command = headless("1");
+    command.arg("--width").arg("wide");
+    let output = run(command);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(!output.stderr.is_empty());
+}
+
+#[test]
+fn out_of_range_sizes_exit_with_the_cli_code() {
+    for args in [["--width", "0"], ["--set", "window.height=0"]] {
+        let mut command = headless("1");
+        command.args(args);
+        assert_eq!(run(command).status.code(), Some(2), "{:?}", args);
+    }
+}